@@ -0,0 +1,7460 @@
+//! Motor de MUD Offline: mundos, partidas y el intérprete de órdenes. El binario
+//! (`src/main.rs`) solo llama a `run`; quien integre el motor usa `World`, `Game` y
+//! `GameError` directamente.
+
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use terminal_size::{Height, Width};
+use std::collections::HashMap;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Copia de la salida (y de lo que se teclea) para `--transcript` / `transcript on`.
+/// `File` no tiene búfer: cada línea llega al disco aunque el juego se cuelgue después.
+static TRANSCRIPT: Mutex<Option<fs::File>> = Mutex::new(None);
+
+#[cfg(test)]
+thread_local! {
+    /// En las pruebas, la salida de cada hilo, para poder comprobarla.
+    static CAPTURED: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+}
+
+fn transcribe(text: &str) {
+    #[cfg(test)]
+    CAPTURED.with(|c| c.borrow_mut().push_str(text));
+    if let Ok(mut guard) = TRANSCRIPT.lock() {
+        if let Some(file) = guard.as_mut() {
+            file.write_all(text.as_bytes()).ok();
+        }
+    }
+}
+
+/// Empieza (o retoma, añadiendo al final) la transcripción en `path`.
+fn start_transcript(path: &str) -> Result<()> {
+    let file = fs::OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|e| anyhow!("No se pudo abrir {path}: {e}"))?;
+    *TRANSCRIPT.lock().map_err(|_| anyhow!("Transcripción no disponible"))? = Some(file);
+    Ok(())
+}
+
+fn transcript_active() -> bool {
+    TRANSCRIPT.lock().is_ok_and(|guard| guard.is_some())
+}
+
+fn stop_transcript() {
+    if let Ok(mut guard) = TRANSCRIPT.lock() {
+        *guard = None;
+    }
+}
+
+// `print!` / `println!` de todo el programa pasan también por `transcribe`.
+macro_rules! print {
+    ($($arg:tt)*) => {{
+        let text = format!($($arg)*);
+        ::std::print!("{text}");
+        transcribe(&text);
+    }};
+}
+
+macro_rules! println {
+    () => {
+        print!("\n")
+    };
+    ($($arg:tt)*) => {{
+        let text = format!($($arg)*);
+        ::std::println!("{text}");
+        transcribe(&text);
+        transcribe("\n");
+    }};
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Item {
+    key: String,
+    name: String,
+    desc: String,
+    portable: bool,
+    effects: HashMap<String, String>,
+    #[serde(default)]
+    container: bool,
+    #[serde(default)]
+    closed: bool, // solo recipientes
+    #[serde(default)]
+    contents: Vec<String>, // solo recipientes
+    #[serde(default)]
+    max_contents: Option<usize>, // capacidad del recipiente; None = sin límite
+    #[serde(default)]
+    suppresses_light: bool, // recipiente que tapa las luces que contiene
+    #[serde(default)]
+    gender: Option<String>, // "m" / "f"; sin él los mensajes no llevan artículo
+    #[serde(default)]
+    plural: bool,
+    #[serde(default)]
+    fuel: Option<u32>, // turnos de luz que le quedan; None = no se consume
+    #[serde(default)]
+    fuel_warned: bool,
+    #[serde(default)]
+    state: String, // estado actual ("unlit", "lit"...); vacío = sin estados
+    #[serde(default)]
+    state_desc: HashMap<String, String>, // descripción por estado
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    state_names: HashMap<String, String>, // nombre en listados por estado ("antorcha encendida")
+    #[serde(default)]
+    consumable: bool, // desaparece al usarlo
+    #[serde(default)]
+    shuffle: bool, // con --shuffle puede aparecer en otra sala
+    #[serde(default)]
+    allowed_rooms: Vec<String>, // salas posibles al barajar; vacío = cualquiera
+    #[serde(default)]
+    unidentified_desc: Option<String>, // lo que se ve hasta identificarlo
+    #[serde(default)]
+    visible_under: Option<String>, // solo se ve con una luz cuyo `reveals` coincida ("uv")
+    #[serde(default)]
+    identified: bool,
+    #[serde(default)]
+    transforms: HashMap<String, String>, // herramientas: objeto destino -> objeto resultante
+    #[serde(default)]
+    under: Vec<String>, // escondido debajo; aparece en la sala con `look under`
+    #[serde(default)]
+    behind: Vec<String>, // escondido detrás; `look behind`
+    #[serde(default)]
+    searched: Vec<String>, // "under" / "behind" ya mirados
+    #[serde(default)]
+    template: bool, // plantilla: cada vez que se coloca es un ejemplar propio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prototype: Option<String>, // en un ejemplar, la plantilla de la que sale
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>, // etiquetas libres ("metal", "llave") para condiciones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    examine_limit: Option<u32>, // veces que se puede examinar; None = sin límite
+    #[serde(default, skip_serializing_if = "is_zero")]
+    examined: u32, // veces examinado, para `examine_limit`
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl Item {
+    /// Clave de la definición: la plantilla en un ejemplar, si no la propia.
+    fn proto(&self) -> &str {
+        self.prototype.as_deref().unwrap_or(&self.key)
+    }
+
+    /// Responde a su clave, a la de su plantilla y a su nombre, también el del estado
+    /// actual (`token` en minúsculas).
+    fn answers_to(&self, token: &str) -> bool {
+        self.key.to_lowercase() == token
+            || self.proto().to_lowercase() == token
+            || self.name.to_lowercase() == token
+            || self.state_names.get(&self.state).is_some_and(|n| n.to_lowercase() == token)
+    }
+
+    /// Nombre para listados: el del estado actual si lo tiene, si no el de siempre.
+    fn listed_name(&self) -> String {
+        self.state_names.get(&self.state).unwrap_or(&self.name).clone()
+    }
+
+    /// Descripción para el estado actual, o la base si no hay una específica.
+    fn current_desc(&self) -> &str {
+        self.state_desc.get(&self.state).unwrap_or(&self.desc)
+    }
+
+    /// Sigue siendo un misterio (tiene `unidentified_desc` y no se ha identificado).
+    fn is_unidentified(&self) -> bool {
+        self.unidentified_desc.is_some() && !self.identified
+    }
+
+    /// Es una luz encendida y con combustible.
+    fn gives_light(&self) -> bool {
+        self.effects.contains_key("lights") && self.fuel != Some(0) && self.state != "unlit"
+    }
+
+    /// Nombre con artículo determinado ("la llave vieja", "los guantes").
+    fn the_name(&self) -> String {
+        let article = match (self.gender.as_deref(), self.plural) {
+            (Some("m"), false) => "el",
+            (Some("f"), false) => "la",
+            (Some("m"), true) => "los",
+            (Some("f"), true) => "las",
+            _ => return self.name.clone(),
+        };
+        format!("{article} {}", self.name)
+    }
+
+    /// Nombre con artículo indeterminado ("un altar de piedra"); sin género, solo el nombre.
+    fn a_name(&self) -> String {
+        let article = match (self.gender.as_deref(), self.plural) {
+            (Some("m"), false) => "un",
+            (Some("f"), false) => "una",
+            (Some("m"), true) => "unos",
+            (Some("f"), true) => "unas",
+            _ => return self.name.clone(),
+        };
+        format!("{article} {}", self.name)
+    }
+
+    /// "de" + nombre con artículo, contrayendo "de el" en "del".
+    fn of_name(&self) -> String {
+        let name = self.the_name();
+        match name.strip_prefix("el ") {
+            Some(rest) => format!("del {rest}"),
+            None => format!("de {name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Room {
+    key: String,
+    name: String,
+    desc: String,
+    exits: HashMap<String, String>, 
+    items: Vec<String>, // suelo
+    flags: HashMap<String, bool>,
+    #[serde(default)]
+    no_drop_message: Option<String>, // con la bandera no_drop
+    #[serde(default)]
+    sounds: Vec<(String, String)>, // (bandera, sonido): se oye mientras la bandera está activa
+    #[serde(default)]
+    requires: HashMap<String, ExitRequirement>, // dirección -> objeto que hay que llevar para pasar
+    #[serde(default)]
+    first_cross_message: HashMap<String, String>, // dirección -> texto la primera vez que se cruza
+}
+
+/// Objeto necesario para cruzar una salida. A diferencia de un bloqueo, se comprueba
+/// cada vez que se pasa.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExitRequirement {
+    item: String,
+    #[serde(default)]
+    message: Option<String>, // negativa propia ("Necesitas una cuerda para bajar.")
+}
+
+impl Room {
+    fn print_sounds(&self) {
+        for (flag, sound) in &self.sounds {
+            if self.flags.get(flag).copied().unwrap_or(false) {
+                println!("{sound}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Player {
+    name: String,
+    location: String,
+    inventory: Vec<String>,
+    #[serde(default = "default_health")]
+    health: u32,
+    #[serde(default = "default_health")]
+    max_health: u32,
+    #[serde(default)]
+    max_items: Option<usize>, // límite de objetos en el inventario
+    #[serde(default)]
+    desc: Option<String>, // lo que dice `examine me`
+    #[serde(default)]
+    gold: u32,
+    #[serde(default)]
+    visited: Vec<String>, // salas por las que ha pasado, destinos de `travel`
+    #[serde(default)]
+    notes: Vec<String>, // cuaderno del comando `note`
+    #[serde(default)]
+    wielded: Option<String>, // arma empuñada; solo cuenta mientras siga en el inventario
+    #[serde(default)]
+    crossed: Vec<String>, // salidas ya cruzadas, "sala:dirección"
+}
+
+impl Player {
+    /// Jugador al empezar partida en `world`.
+    fn new(world: &World, difficulty: &Difficulty) -> Self {
+        Player {
+            name: "Hero".into(),
+            location: world.start.clone(),
+            inventory: vec![],
+            health: default_health(),
+            max_health: default_health(),
+            max_items: difficulty.carry_limit(world.max_items),
+            desc: None,
+            gold: 0,
+            visited: vec![world.start.clone()],
+            notes: vec![],
+            wielded: None,
+            crossed: vec![],
+        }
+    }
+}
+
+fn default_health() -> u32 {
+    100
+}
+
+/// Objetos del suelo que `look` enumera antes de resumirlos en una cuenta.
+const LOOK_ITEM_LIMIT: usize = 8;
+
+/// Daño de `attack` sin arma empuñada.
+const FIST_DAMAGE: u32 = 1;
+
+/// Con este combustible restante se avisa (una vez) de que la luz se va a apagar.
+const FUEL_WARNING: u32 = 3;
+/// Turnos restantes a partir de los cuales se avisa del límite.
+const TURN_WARNING: u64 = 5;
+
+/// Por debajo de esta salud se avisa al jugador en cada turno que pierde vida.
+const LOW_HEALTH: u32 = 25;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Npc {
+    key: String,
+    name: String,
+    desc: String,
+    location: String,
+    #[serde(default)]
+    patrol: Vec<String>, // salas que recorre, en bucle
+    #[serde(default)]
+    patrol_idx: usize,
+    #[serde(default)]
+    desc_variants: Vec<(String, String)>, // (bandera de la sala, descripción); gana la primera activa
+    #[serde(default)]
+    sells: HashMap<String, u32>, // objeto (o plantilla) -> precio al que lo vende
+    #[serde(default)]
+    buys: HashMap<String, u32>, // objeto (o plantilla) -> precio que paga por él
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stock: Option<Vec<String>>, // lo que tiene a la venta; sin él, todo lo de `sells`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    health: Option<u32>, // sin ella no se le puede atacar
+    #[serde(default)]
+    attack: u32, // daño que devuelve a cada ataque
+    #[serde(default)]
+    items: Vec<String>, // lo que lleva; cae al suelo al derrotarlo
+}
+
+impl Npc {
+    /// Descripción según las banderas de la sala donde está; si ninguna aplica, `desc`.
+    fn current_desc(&self, flags: &HashMap<String, bool>) -> &str {
+        self.desc_variants
+            .iter()
+            .find(|(flag, _)| flags.get(flag).copied().unwrap_or(false))
+            .map_or(&self.desc, |(_, text)| text)
+    }
+
+    fn is_shopkeeper(&self) -> bool {
+        !self.sells.is_empty() || !self.buys.is_empty()
+    }
+
+    /// Objetos que puede vender ahora mismo (los de `stock` cuya plantilla tiene precio
+    /// en `sells`).
+    fn for_sale(&self, items: &IndexMap<String, Item>) -> Vec<String> {
+        let mut keys: Vec<String> = match &self.stock {
+            Some(stock) => stock.clone(),
+            None => self.sells.keys().cloned().collect(),
+        };
+        if self.stock.is_none() {
+            keys.sort();
+        }
+        keys.retain(|k| items.get(k).is_some_and(|it| self.sells.contains_key(it.proto())));
+        keys
+    }
+
+    /// Responde a su clave, a su nombre y al nombre sin artículo ("guardián").
+    fn matches(&self, token: &str) -> bool {
+        let name = self.name.to_lowercase();
+        let bare = name.split_once(' ').filter(|(art, _)| ["el", "la", "los", "las"].contains(art));
+        self.key.to_lowercase() == token || name == token || bare.is_some_and(|(_, rest)| rest == token)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct World {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    world_id: Option<String>, // identificador estable del mundo, para informes de fallos
+    #[serde(default)]
+    rooms: IndexMap<String, Room>,
+    #[serde(default)]
+    items: IndexMap<String, Item>,
+    #[serde(default)]
+    npcs: IndexMap<String, Npc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>, // otros JSON (relativos a este) cuyas salas/objetos se fusionan
+    #[serde(default = "default_start")]
+    start: String, // sala inicial del jugador
+    #[serde(default)]
+    auto_reverse: bool, // crear las salidas de vuelta que falten al cargar
+    #[serde(default)]
+    dark_blocks_movement: bool, // sin luz, en una sala oscura no se puede avanzar
+    #[serde(default)]
+    max_items: Option<usize>, // límite de objetos del jugador al empezar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    full_message: Option<String>, // negativa con el inventario lleno
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unknown_command_message: Option<String>, // respuesta a un comando desconocido; admite {input}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    look_item_limit: Option<usize>, // con más objetos en el suelo, `look` solo da la cuenta
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flag_patterns: Vec<String>, // banderas propias admitidas ("water", "rune_*"); con ellas se avisa de las demás
+    #[serde(default)]
+    opposites: HashMap<String, String>, // pares de direcciones propios ("in" -> "out")
+    #[serde(default)]
+    direction_names: HashMap<String, String>, // nombre visible de cada dirección ("north" -> "proa")
+    #[serde(default)]
+    walkthrough: Vec<String>, // solución paso a paso, para el comando `solution`
+    #[serde(default)]
+    turn_limit: Option<u64>, // partida contrarreloj: turnos disponibles
+    #[serde(default)]
+    difficulty: Option<Level>, // nivel por defecto del mundo; --difficulty manda
+    #[serde(default)]
+    difficulty_overrides: DifficultyOverrides, // ajustes sueltos que pisan al nivel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    win: Option<Condition>, // al cumplirse termina la partida con un final
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    endings: Vec<Ending>, // se muestra el primero cuya condición se cumpla
+    #[serde(skip)]
+    room_dir: Option<PathBuf>, // mundo por partes: de aquí se leen las salas que faltan
+}
+
+/// Estado que se puede exigir (victoria, finales). Todo lo indicado ha de cumplirse;
+/// una condición vacía se cumple siempre.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Condition {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    carrying: Vec<String>, // objetos en el inventario
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    not_carrying: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    carrying_tag: Vec<String>, // por cada etiqueta, algún objeto del inventario que la lleve
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>, // "sala:bandera" activas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    room: Option<String>, // sala donde está el jugador
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Ending {
+    #[serde(default)]
+    condition: Condition,
+    text: String,
+}
+
+/// Banderas de sala que entiende el motor; las demás han de declararse en
+/// `flag_patterns` para que no se avise de ellas.
+const ENGINE_FLAGS: &[&str] = &["dark", "no_drop", "locked_*"];
+
+/// Final si el mundo no define ninguno que encaje.
+const DEFAULT_ENDING: &str = "¡Lo has conseguido! Tu aventura termina aquí.";
+
+/// Subdirectorio de un mundo por partes con un JSON por sala.
+const ROOMS_SUBDIR: &str = "rooms";
+
+/// Origen de las salas que aún no están en `World::rooms`. Con el almacén en memoria
+/// todas se cargan al empezar; con el de directorio, cada una al entrar por primera vez,
+/// y se queda en `World::rooms` como caché.
+trait RoomStore: std::fmt::Debug {
+    /// La sala `key` si existe fuera de memoria.
+    fn fetch(&self, key: &str) -> Result<Option<Room>, GameError>;
+}
+
+/// Todas las salas están ya en el `World`.
+#[derive(Debug)]
+struct MemoryStore;
+
+impl RoomStore for MemoryStore {
+    fn fetch(&self, _key: &str) -> Result<Option<Room>, GameError> {
+        Ok(None)
+    }
+}
+
+/// Una sala por archivo (`<clave>.json`) en `dir`. Las claves vienen de salidas y de
+/// partidas guardadas, así que solo valen las de `valid_name`: nada de salir de `dir`.
+#[derive(Debug)]
+struct LazyDirStore {
+    dir: PathBuf,
+}
+
+impl RoomStore for LazyDirStore {
+    fn fetch(&self, key: &str) -> Result<Option<Room>, GameError> {
+        if !valid_name(key) {
+            return Err(GameError::Validation(vec![format!("Clave de sala no válida: '{key}'")]));
+        }
+        let path = self.dir.join(format!("{key}.json"));
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(&path)?;
+        let room = serde_json::from_str(&data).map_err(|e| GameError::parse(&path, &e))?;
+        Ok(Some(room))
+    }
+}
+
+/// Errores al leer o escribir mundos y partidas, para quien integre el motor y quiera
+/// distinguirlos sin analizar el texto. El binario se limita a mostrarlos.
+#[derive(Debug, thiserror::Error)]
+pub enum GameError {
+    /// No existe el archivo o la carpeta del mundo.
+    #[error("No existe el archivo {}", .0.display())]
+    WorldNotFound(PathBuf),
+    /// JSON mal formado (de un mundo o de una partida).
+    #[error("Error al leer {} (línea {line}, columna {column}): {message}", path.display())]
+    ParseError { path: PathBuf, line: usize, column: usize, message: String },
+    /// El mundo se lee pero no se puede jugar (sala inicial, inclusiones...).
+    #[error("{}", .0.join("; "))]
+    Validation(Vec<String>),
+    /// La partida no encaja con este mundo o esta versión.
+    #[error("{0}")]
+    InvalidSave(String),
+    /// La partida no se puede convertir a JSON.
+    #[error("No se pudo preparar la partida: {0}")]
+    Serialize(#[source] serde_json::Error),
+    /// Fallo de lectura o escritura; una partida que no existe es `NotFound`.
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+impl GameError {
+    /// `ParseError` a partir del error de serde; su mensaje ya trae " at line X column
+    /// Y", que se da en castellano aparte.
+    fn parse(path: &Path, e: &serde_json::Error) -> Self {
+        let msg = e.to_string();
+        GameError::ParseError {
+            path: path.to_path_buf(),
+            line: e.line(),
+            column: e.column(),
+            message: msg.split(" at line ").next().unwrap_or(&msg).to_string(),
+        }
+    }
+}
+
+fn default_start() -> String {
+    "cave_entrance".into()
+}
+
+impl World {
+    /// Lee un mundo de un JSON (con sus `include`) o de una carpeta por partes.
+    pub fn load_from_file(path: &str) -> Result<Self, GameError> {
+        if !Path::new(path).exists() {
+            return Err(GameError::WorldNotFound(PathBuf::from(path)));
+        }
+        if Path::new(path).is_dir() {
+            return Self::load_from_dir(Path::new(path));
+        }
+        let mut world = Self::read_with_includes(Path::new(path), &mut Vec::new())?;
+        if !world.rooms.contains_key(&world.start) {
+            return Err(GameError::Validation(vec![format!("La sala inicial '{}' no existe en {path}", world.start)]));
+        }
+        world.instantiate_all();
+        Ok(world)
+    }
+
+    /// `instantiate_room` en todas las salas cargadas, y lo mismo con lo que lleva cada
+    /// PNJ y lo que guarda cada objeto (contenido, `under`, `behind`).
+    fn instantiate_all(&mut self) {
+        let rooms: Vec<String> = self.rooms.keys().cloned().collect();
+        for key in rooms {
+            self.instantiate_room(&key);
+        }
+        let npcs: Vec<String> = self.npcs.keys().cloned().collect();
+        for key in npcs {
+            let Some(mut carried) = self.npcs.get(&key).map(|n| n.items.clone()) else {
+                continue;
+            };
+            let created = self.instantiate(&key, &mut carried);
+            if let Some(npc) = self.npcs.get_mut(&key) {
+                npc.items = carried;
+            }
+            self.instantiate_inside(created);
+        }
+        let items: Vec<String> = self.items.values().filter(|it| !it.template).map(|it| it.key.clone()).collect();
+        self.instantiate_inside(items);
+    }
+
+    /// Cambia cada plantilla colocada en la sala `rkey` (y en lo que contenga) por un
+    /// ejemplar propio, `<plantilla>@<sitio>` (con `#n` si se repite en el mismo sitio).
+    /// Las claves solo dependen del sitio, así que salen iguales al cargar una partida
+    /// o al leer la sala más tarde (`LazyDirStore`).
+    fn instantiate_room(&mut self, rkey: &str) {
+        let Some(mut keys) = self.rooms.get(rkey).map(|r| r.items.clone()) else {
+            return;
+        };
+        let created = self.instantiate(rkey, &mut keys);
+        if let Some(room) = self.rooms.get_mut(rkey) {
+            room.items = keys;
+        }
+        // los ejemplares traen el contenido y los escondites de la plantilla
+        self.instantiate_inside(created);
+    }
+
+    /// `instantiate` en el contenido, `under` y `behind` de los objetos `keys`, y luego
+    /// en los de cada ejemplar que salga de ahí. Las tres listas comparten sitio (el
+    /// objeto), así que se numeran juntas para no repetir clave.
+    fn instantiate_inside(&mut self, mut keys: Vec<String>) {
+        while let Some(key) = keys.pop() {
+            let Some(item) = self.items.get(&key) else {
+                continue;
+            };
+            let (n_contents, n_under) = (item.contents.len(), item.under.len());
+            let mut all = [item.contents.clone(), item.under.clone(), item.behind.clone()].concat();
+            keys.extend(self.instantiate(&key, &mut all));
+            let behind = all.split_off(n_contents + n_under);
+            let under = all.split_off(n_contents);
+            if let Some(it) = self.items.get_mut(&key) {
+                it.contents = all;
+                it.under = under;
+                it.behind = behind;
+            }
+        }
+    }
+
+    /// Ejemplar nuevo de la plantilla `proto` para `place` (`<plantilla>@<sitio>`, con
+    /// `#n` si ya hay otros ahí), como lo que vende un comerciante. `None` si `proto`
+    /// no es una plantilla.
+    fn spawn_instance(&mut self, proto: &str, place: &str) -> Option<String> {
+        let id = (1..)
+            .map(|n| if n == 1 { format!("{proto}@{place}") } else { format!("{proto}@{place}#{n}") })
+            .find(|id| !self.items.contains_key(id))?;
+        self.add_instance(proto, &id).then_some(id)
+    }
+
+    /// Crea el ejemplar `id` de la plantilla `proto`; false si `proto` no es plantilla.
+    fn add_instance(&mut self, proto: &str, id: &str) -> bool {
+        let Some(mut item) = self.items.get(proto).filter(|it| it.template).cloned() else {
+            return false;
+        };
+        item.key = id.to_string();
+        item.template = false;
+        item.prototype = Some(proto.to_string());
+        self.items.insert(id.to_string(), item);
+        true
+    }
+
+    /// Sustituye en `keys` las plantillas por ejemplares de `place`; devuelve los creados.
+    fn instantiate(&mut self, place: &str, keys: &mut [String]) -> Vec<String> {
+        let mut created = Vec::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for key in keys.iter_mut() {
+            if !self.items.get(key.as_str()).is_some_and(|it| it.template) {
+                continue;
+            }
+            let n = seen.entry(key.clone()).or_insert(0);
+            *n += 1;
+            let id = if *n == 1 { format!("{key}@{place}") } else { format!("{key}@{place}#{n}") };
+            if !self.items.contains_key(&id) && self.add_instance(key, &id) {
+                created.push(id.clone());
+            }
+            *key = id;
+        }
+        created
+    }
+
+    /// Mundo por partes: `dir/world.json` con todo salvo (si se quiere) las salas, y una
+    /// `dir/rooms/<clave>.json` por sala, que se lee al necesitarla (ver `LazyDirStore`).
+    /// La sala inicial se lee ya, para que sus errores salgan al cargar.
+    fn load_from_dir(dir: &Path) -> Result<Self, GameError> {
+        let mut world = Self::read_with_includes(&dir.join("world.json"), &mut Vec::new())?;
+        let rooms = dir.join(ROOMS_SUBDIR);
+        if !world.rooms.contains_key(&world.start) {
+            let Some(room) = (LazyDirStore { dir: rooms.clone() }).fetch(&world.start)? else {
+                return Err(GameError::Validation(vec![format!("La sala inicial '{}' no existe en {}", world.start, dir.display())]));
+            };
+            world.rooms.insert(world.start.clone(), room);
+        }
+        world.room_dir = Some(rooms);
+        world.instantiate_all();
+        Ok(world)
+    }
+
+    /// Lee ya todas las salas de un mundo por partes (para `validate`, las mediciones y
+    /// lo que recorre el mapa entero al empezar, como `--shuffle`).
+    fn load_all_rooms(&mut self) -> Result<(), GameError> {
+        let Some(dir) = self.room_dir.clone() else {
+            return Ok(());
+        };
+        let store = LazyDirStore { dir: dir.clone() };
+        let mut keys: Vec<String> = fs::read_dir(&dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str()?.strip_suffix(".json").map(String::from))
+            .collect();
+        keys.sort();
+        for key in keys {
+            if self.rooms.contains_key(&key) {
+                continue;
+            }
+            if let Some(room) = store.fetch(&key)? {
+                self.rooms.insert(key.clone(), room);
+                self.instantiate_room(&key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Salas alcanzables desde `from` sin cruzar ninguna salida bloqueada.
+    fn reachable_unlocked(&self, from: &str) -> Vec<String> {
+        self.reachable(from, true)
+    }
+
+    /// Salas alcanzables desde `from`; con `honor_locks` no se cruzan salidas bloqueadas.
+    fn reachable(&self, from: &str, honor_locks: bool) -> Vec<String> {
+        let mut seen = vec![from.to_string()];
+        let mut i = 0;
+        while i < seen.len() {
+            if let Some(room) = self.rooms.get(&seen[i]) {
+                let mut exits: Vec<(&String, &String)> = room.exits.iter().collect();
+                exits.sort();
+                for (dir, dest) in exits {
+                    let locked = honor_locks && room.flags.get(&format!("locked_{dir}")).copied().unwrap_or(false);
+                    if !locked && self.rooms.contains_key(dest) && !seen.contains(dest) {
+                        seen.push(dest.clone());
+                    }
+                }
+            }
+            i += 1;
+        }
+        seen
+    }
+
+    /// Reparte al azar los objetos portátiles marcados con `shuffle` que estén en el
+    /// suelo de alguna sala, respetando `allowed_rooms`. Garantía de resolubilidad
+    /// (básica): un objeto con efecto `unlocks` solo puede caer en salas alcanzables
+    /// desde el inicio sin abrir ninguna cerradura, así que ninguna llave queda tras la
+    /// puerta que abre ni tras otra cerrada. No se comprueban otros requisitos (luz,
+    /// recipientes, PNJs). Si un objeto no tiene sala válida, se queda donde estaba.
+    fn shuffle_items(&mut self, rng: &mut Rng) {
+        let open = self.reachable_unlocked(&self.start);
+        let all: Vec<String> = self.rooms.keys().cloned().collect();
+        let mut placed = Vec::new();
+        for room in self.rooms.values_mut() {
+            let items = &self.items;
+            room.items.retain(|k| {
+                let movable = items.get(k).is_some_and(|it| it.shuffle && it.portable);
+                if movable {
+                    placed.push((k.clone(), room.key.clone()));
+                }
+                !movable
+            });
+        }
+        for (key, original) in placed {
+            let item = &self.items[&key];
+            let is_key = item.effects.contains_key("unlocks") && !item.effects.contains_key("switch");
+            let candidates: Vec<&String> = all
+                .iter()
+                .filter(|r| item.allowed_rooms.is_empty() || item.allowed_rooms.contains(r))
+                .filter(|r| !is_key || open.contains(r))
+                .collect();
+            let dest = match candidates.len() {
+                0 => original,
+                n => candidates[rng.below(n)].clone(),
+            };
+            if let Some(r) = self.rooms.get_mut(&dest) {
+                r.items.push(key);
+            }
+        }
+    }
+
+    /// Lee un archivo y fusiona sus `include` en orden; cada archivo sobrescribe las
+    /// claves de los anteriores. `stack` lleva la cadena de inclusiones en curso.
+    fn read_with_includes(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Self, GameError> {
+        let canon = fs::canonicalize(path).map_err(|_| GameError::WorldNotFound(path.to_path_buf()))?;
+        if let Some(pos) = stack.iter().position(|p| p == &canon) {
+            let chain: Vec<String> = stack[pos..]
+                .iter()
+                .chain([&canon])
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(GameError::Validation(vec![format!("Inclusión circular: {}", chain.join(" -> "))]));
+        }
+        let data = fs::read_to_string(&canon)?;
+        if data.trim().is_empty() {
+            return Err(GameError::Validation(vec![format!("El archivo del mundo está vacío: {}", path.display())]));
+        }
+        let mut world: World = serde_json::from_str(&data).map_err(|e| GameError::parse(path, &e))?;
+        let base = canon.parent().map(Path::to_path_buf).unwrap_or_default();
+        stack.push(canon);
+        for inc in std::mem::take(&mut world.include) {
+            let part = Self::read_with_includes(&base.join(inc), stack)?;
+            world.rooms.extend(part.rooms);
+            world.items.extend(part.items);
+            world.npcs.extend(part.npcs);
+        }
+        stack.pop();
+        Ok(world)
+    }
+
+    /// Camino más corto (en direcciones) de `from` a `to` por anchura. Con `honor_locks`
+    /// no se cruzan las salidas con `locked_<dir>` activa.
+    /// Con `known`, solo se pasa por esas salas.
+    fn find_path(&self, from: &str, to: &str, honor_locks: bool, known: Option<&[String]>) -> Option<Vec<String>> {
+        let mut prev: HashMap<&str, (&str, &str)> = HashMap::new();
+        let mut queue = std::collections::VecDeque::from([from]);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                let mut dirs = Vec::new();
+                let mut node = cur;
+                while let Some(&(p, dir)) = prev.get(node) {
+                    dirs.push(dir.to_string());
+                    node = p;
+                }
+                dirs.reverse();
+                return Some(dirs);
+            }
+            let Some(room) = self.rooms.get(cur) else {
+                continue;
+            };
+            let mut exits: Vec<(&String, &String)> = room.exits.iter().collect();
+            exits.sort();
+            for (dir, dest) in exits {
+                if honor_locks && room.flags.get(&format!("locked_{dir}")).copied().unwrap_or(false) {
+                    continue;
+                }
+                if known.is_some_and(|k| !k.contains(dest)) {
+                    continue;
+                }
+                if dest != from && !prev.contains_key(dest.as_str()) {
+                    prev.insert(dest, (cur, dir));
+                    queue.push_back(dest);
+                }
+            }
+        }
+        None
+    }
+
+    /// Nombre con que se muestra `dir` (el propio `dir` si el mundo no lo renombra).
+    fn dir_label<'a>(&'a self, dir: &'a str) -> &'a str {
+        self.direction_names.get(dir).map_or(dir, String::as_str)
+    }
+
+    /// Dirección interna de lo que escribe el jugador: acepta la clave, el nombre visible
+    /// y las abreviaturas de `DIR_ABBREVIATIONS`.
+    fn canonical_dir(&self, input: &str) -> String {
+        let input = input.to_lowercase();
+        if let Some((dir, _)) = self.direction_names.iter().find(|(_, label)| label.to_lowercase() == input) {
+            return dir.clone();
+        }
+        DIR_ABBREVIATIONS
+            .iter()
+            .find(|(abbr, _)| *abbr == input)
+            .map_or(input, |(_, dir)| dir.to_string())
+    }
+
+    /// `input` nombra una dirección que el mundo conoce: una de las de siempre, una
+    /// con nombre u opuesto propio, o la de alguna salida de las salas cargadas.
+    fn is_direction(&self, input: &str) -> bool {
+        let dir = self.canonical_dir(input);
+        opposite_dir(&dir).is_some()
+            || self.direction_names.contains_key(&dir)
+            || self.opposites.contains_key(&dir)
+            || self.rooms.values().any(|r| r.exits.contains_key(&dir))
+    }
+
+    /// Dirección opuesta: primero los pares del mundo, luego los cardinales de siempre.
+    fn opposite(&self, dir: &str) -> Option<String> {
+        self.opposites
+            .get(dir)
+            .cloned()
+            .or_else(|| opposite_dir(dir).map(String::from))
+    }
+
+    /// Avisos por objetos colocados en más de un sitio (suelo de una sala o
+    /// interior de un recipiente): al cogerlos se duplicarían.
+    fn item_location_warnings(&self) -> Vec<String> {
+        let mut seen: IndexMap<&str, Vec<String>> = IndexMap::new();
+        for room in self.rooms.values() {
+            for key in &room.items {
+                seen.entry(key).or_default().push(format!("sala {}", room.key));
+            }
+        }
+        for item in self.items.values() {
+            for key in &item.contents {
+                seen.entry(key).or_default().push(format!("recipiente {}", item.key));
+            }
+            for key in item.under.iter().chain(&item.behind) {
+                seen.entry(key).or_default().push(format!("escondido en {}", item.key));
+            }
+        }
+        for npc in self.npcs.values() {
+            for key in &npc.items {
+                seen.entry(key).or_default().push(format!("pnj {}", npc.key));
+            }
+        }
+        seen.into_iter()
+            .filter(|(_, places)| places.len() > 1)
+            .map(|(key, places)| format!("el objeto '{key}' aparece en varios sitios: {}", places.join(", ")))
+            .collect()
+    }
+
+    /// Todas las comprobaciones de estructura, para `validate`. Errores: sala inicial,
+    /// salidas, objetos, PNJs y `unlocks` que apuntan a algo que no existe. Avisos: salas
+    /// inalcanzables, salidas de un solo sentido, objetos repetidos, `opposites`,
+    /// textos desmesurados, cerraduras sin llave y banderas desconocidas.
+    fn validate(&self) -> Vec<Finding> {
+        let mut out = Vec::new();
+        let mut error = |category, message| out.push(Finding { error: true, category, message });
+        if !self.rooms.contains_key(&self.start) {
+            error("inicio", format!("la sala inicial '{}' no existe", self.start));
+        }
+        for room in self.rooms.values() {
+            let mut exits: Vec<(&String, &String)> = room.exits.iter().collect();
+            exits.sort();
+            for (dir, dest) in exits {
+                if !self.rooms.contains_key(dest) {
+                    error("salidas", format!("{} {dir} -> '{dest}', que no existe", room.key));
+                }
+            }
+            for key in &room.items {
+                if !self.items.contains_key(key) {
+                    error("objetos", format!("la sala {} contiene '{key}', que no existe", room.key));
+                }
+            }
+            let mut reqs: Vec<(&String, &ExitRequirement)> = room.requires.iter().collect();
+            reqs.sort_by_key(|(dir, _)| dir.as_str());
+            for (dir, req) in reqs {
+                if !self.items.contains_key(&req.item) {
+                    error("objetos", format!("{} {dir} requiere '{}', que no existe", room.key, req.item));
+                }
+            }
+        }
+        for item in self.items.values() {
+            for key in &item.contents {
+                if !self.items.contains_key(key) {
+                    error("objetos", format!("el recipiente {} contiene '{key}', que no existe", item.key));
+                }
+            }
+            for key in item.under.iter().chain(&item.behind) {
+                if !self.items.contains_key(key) {
+                    error("objetos", format!("{} esconde '{key}', que no existe", item.key));
+                }
+            }
+            let mut transforms: Vec<(&String, &String)> = item.transforms.iter().collect();
+            transforms.sort();
+            for (from, to) in transforms {
+                for key in [from, to].into_iter().filter(|k| !self.items.contains_key(*k)) {
+                    error("objetos", format!("{} transforma con '{key}', que no existe", item.key));
+                }
+            }
+            if let Some(tag) = item.effects.get("unlocks") {
+                match tag.split_once(':') {
+                    Some((rkey, dir)) => match self.rooms.get(rkey) {
+                        Some(room) if room.exits.contains_key(dir) => {}
+                        Some(_) => error("unlocks", format!("{}: la sala {rkey} no tiene salida {dir}", item.key)),
+                        None => error("unlocks", format!("{}: la sala '{rkey}' no existe", item.key)),
+                    },
+                    None => error("unlocks", format!("{}: '{tag}' no tiene la forma sala:dirección", item.key)),
+                }
+            }
+        }
+        for npc in self.npcs.values() {
+            for key in npc.items.iter().filter(|k| !self.items.contains_key(*k)) {
+                error("objetos", format!("{} lleva '{key}', que no existe", npc.key));
+            }
+            for room in std::iter::once(&npc.location).chain(&npc.patrol) {
+                if !self.rooms.contains_key(room) {
+                    error("pnjs", format!("{} va a la sala '{room}', que no existe", npc.key));
+                }
+            }
+        }
+
+        let mut warn = |category, message| out.push(Finding { error: false, category, message });
+        if self.rooms.contains_key(&self.start) {
+            let reachable = self.reachable(&self.start, false);
+            for key in self.rooms.keys().filter(|k| !reachable.contains(k)) {
+                warn("alcance", format!("la sala {key} no se puede alcanzar desde {}", self.start));
+            }
+        }
+        for room in self.rooms.values() {
+            let mut exits: Vec<(&String, &String)> = room.exits.iter().collect();
+            exits.sort();
+            for (dir, dest) in exits {
+                if self.rooms.get(dest).is_some_and(|d| !d.exits.values().any(|back| back == &room.key)) {
+                    warn("sentido", format!("{} {dir} -> {dest} no tiene vuelta", room.key));
+                }
+            }
+        }
+        for w in self.item_location_warnings() {
+            warn("objetos", w);
+        }
+        for w in self.opposite_warnings() {
+            warn("opuestos", w);
+        }
+        for w in self.text_warnings() {
+            warn("texto", w);
+        }
+        for w in self.keyless_lock_warnings() {
+            warn("cerraduras", w);
+        }
+        for w in self.flag_warnings() {
+            warn("banderas", w);
+        }
+        out
+    }
+
+    /// Con `flag_patterns`, avisos por banderas de sala que no encajan ni con las del
+    /// motor (`ENGINE_FLAGS`) ni con las declaradas: casi siempre erratas ("lockd_north").
+    /// Un patrón que acaba en `*` admite cualquier final.
+    fn flag_warnings(&self) -> Vec<String> {
+        if self.flag_patterns.is_empty() {
+            return vec![];
+        }
+        let patterns: Vec<&str> = ENGINE_FLAGS.iter().copied().chain(self.flag_patterns.iter().map(String::as_str)).collect();
+        let known = |flag: &str| {
+            patterns.iter().any(|p| match p.strip_suffix('*') {
+                Some(prefix) => flag.starts_with(prefix),
+                None => flag == *p,
+            })
+        };
+        let mut out = Vec::new();
+        for room in self.rooms.values() {
+            let mut flags: Vec<&String> = room.flags.keys().filter(|f| !known(f)).collect();
+            flags.sort();
+            for f in flags {
+                out.push(format!("la sala {} usa la bandera desconocida '{f}'", room.key));
+            }
+        }
+        out
+    }
+
+    /// Avisos por salidas que empiezan con `locked_<dir>` y ningún objeto con
+    /// `unlocks` "sala:dir" (llave o interruptor) puede abrir: cerraduras sin solución.
+    fn keyless_lock_warnings(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        for room in self.rooms.values() {
+            let mut dirs: Vec<&str> = room
+                .flags
+                .iter()
+                .filter(|(_, &on)| on)
+                .filter_map(|(f, _)| f.strip_prefix("locked_"))
+                .collect();
+            dirs.sort();
+            for dir in dirs {
+                let tag = format!("{}:{dir}", room.key);
+                if !self.items.values().any(|it| it.effects.get("unlocks") == Some(&tag)) {
+                    out.push(format!("{} {dir} está cerrada y ningún objeto la abre", room.key));
+                }
+            }
+        }
+        out
+    }
+
+    /// Avisos por nombres de más de `MAX_NAME_CHARS` y descripciones con tiradas de más
+    /// de `MAX_UNBROKEN_CHARS` sin espacios (casi siempre basura pegada por error).
+    fn text_warnings(&self) -> Vec<String> {
+        let mut texts: Vec<(String, &str, &str)> = Vec::new(); // (quién, nombre, descripciones)
+        for r in self.rooms.values() {
+            texts.push((format!("sala {}", r.key), &r.name, &r.desc));
+        }
+        for it in self.items.values() {
+            texts.push((format!("objeto {}", it.key), &it.name, &it.desc));
+            for d in it.state_desc.values().chain(&it.unidentified_desc) {
+                texts.push((format!("objeto {}", it.key), "", d));
+            }
+        }
+        for n in self.npcs.values() {
+            texts.push((format!("pnj {}", n.key), &n.name, &n.desc));
+            for (_, d) in &n.desc_variants {
+                texts.push((format!("pnj {}", n.key), "", d));
+            }
+        }
+        let mut out = Vec::new();
+        for (who, name, desc) in texts {
+            let len = name.chars().count();
+            if len > MAX_NAME_CHARS {
+                out.push(format!("{who}: el nombre tiene {len} caracteres (máximo {MAX_NAME_CHARS})"));
+            }
+            if let Some(run) = desc.split_whitespace().map(|w| w.chars().count()).find(|&n| n > MAX_UNBROKEN_CHARS) {
+                out.push(format!("{who}: la descripción tiene {run} caracteres seguidos sin espacios"));
+            }
+        }
+        out
+    }
+
+    /// Avisos por pares de `opposites` que no son simétricos.
+    fn opposite_warnings(&self) -> Vec<String> {
+        let mut keys: Vec<&String> = self.opposites.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .filter_map(|a| {
+                let b = &self.opposites[a];
+                match self.opposite(b) {
+                    Some(back) if &back == a => None,
+                    Some(back) => Some(format!("'{a}' tiene como opuesto '{b}', pero el opuesto de '{b}' es '{back}'")),
+                    None => Some(format!("'{a}' tiene como opuesto '{b}', pero '{b}' no tiene opuesto")),
+                }
+            })
+            .collect()
+    }
+
+    /// Añade a cada sala destino la salida opuesta que le falte. Las salidas ya
+    /// definidas nunca se sobrescriben. Devuelve los enlaces creados ("sala dir -> destino").
+    fn link_reverse_exits(&mut self) -> Vec<String> {
+        let mut links = Vec::new();
+        for (key, room) in &self.rooms {
+            let mut dirs: Vec<&String> = room.exits.keys().collect();
+            dirs.sort();
+            for dir in dirs {
+                let dest = &room.exits[dir];
+                let Some(back) = self.opposite(dir) else {
+                    continue;
+                };
+                if let Some(d) = self.rooms.get(dest) {
+                    let link = (dest.clone(), back, key.clone());
+                    if !d.exits.contains_key(&link.1) && !links.contains(&link) {
+                        links.push(link);
+                    }
+                }
+            }
+        }
+        let mut notes = Vec::new();
+        for (from, dir, to) in links {
+            if let Some(r) = self.rooms.get_mut(&from) {
+                r.exits.insert(dir.clone(), to.clone());
+                notes.push(format!("{from} {dir} -> {to}"));
+            }
+        }
+        notes
+    }
+}
+
+/// Nivel de dificultad elegido con `--difficulty` o con `difficulty` en el mundo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Level {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "easy" | "facil" | "fácil" => Some(Level::Easy),
+            "normal" => Some(Level::Normal),
+            "hard" | "dificil" | "difícil" => Some(Level::Hard),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Easy => "easy",
+            Level::Normal => "normal",
+            Level::Hard => "hard",
+        }
+    }
+}
+
+/// Ajustes mecánicos que consultan los sistemas de juego en lugar de banderas sueltas.
+///
+/// | ajuste                 | easy  | normal | hard  |
+/// |------------------------|-------|--------|-------|
+/// | `fuel_burns`           | false | true   | true  |
+/// | `damage_percent`       | 50    | 100    | 100   |
+/// | `free_hints`           | true  | false  | false |
+/// | `dark_blocks_movement` | false | false  | true  |
+/// | `carry_percent`        | 100   | 100    | 50    |
+///
+/// `carry_percent` solo actúa si el mundo fija `max_items`. El antiguo
+/// `dark_blocks_movement: true` del mundo sigue activando el bloqueo en cualquier nivel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Difficulty {
+    level: Level,
+    fuel_burns: bool,      // las luces gastan combustible
+    damage_percent: u32,   // porcentaje del daño por veneno que se aplica
+    free_hints: bool,      // `solution` no pide confirmación ni marca la partida
+    dark_blocks_movement: bool,
+    carry_percent: u32,    // porcentaje de `max_items` que puede llevar el jugador
+}
+
+/// Ajustes de dificultad que un mundo fija a mano; los ausentes los decide el nivel.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DifficultyOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fuel_burns: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    damage_percent: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    free_hints: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dark_blocks_movement: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    carry_percent: Option<u32>,
+}
+
+impl Difficulty {
+    fn for_level(level: Level) -> Self {
+        let easy = level == Level::Easy;
+        let hard = level == Level::Hard;
+        Self {
+            level,
+            fuel_burns: !easy,
+            damage_percent: if easy { 50 } else { 100 },
+            free_hints: easy,
+            dark_blocks_movement: hard,
+            carry_percent: if hard { 50 } else { 100 },
+        }
+    }
+
+    /// Nivel de `cli` (o el del mundo, o normal) con los ajustes propios del mundo encima.
+    fn resolve(world: &World, cli: Option<Level>) -> Self {
+        let mut d = Self::for_level(cli.or(world.difficulty).unwrap_or_default());
+        let o = &world.difficulty_overrides;
+        d.fuel_burns = o.fuel_burns.unwrap_or(d.fuel_burns);
+        d.damage_percent = o.damage_percent.unwrap_or(d.damage_percent);
+        d.free_hints = o.free_hints.unwrap_or(d.free_hints);
+        d.dark_blocks_movement = o.dark_blocks_movement.unwrap_or(d.dark_blocks_movement) || world.dark_blocks_movement;
+        d.carry_percent = o.carry_percent.unwrap_or(d.carry_percent);
+        d
+    }
+
+    /// Límite de objetos tras aplicar `carry_percent`; nunca baja de uno.
+    fn carry_limit(&self, max_items: Option<usize>) -> Option<usize> {
+        max_items.map(|n| (n * self.carry_percent as usize / 100).max(1))
+    }
+}
+
+/// Límites de `World::text_warnings`.
+const MAX_NAME_CHARS: usize = 60;
+const MAX_UNBROKEN_CHARS: usize = 200;
+
+/// Resultado de una comprobación de `World::validate`.
+#[derive(Debug)]
+struct Finding {
+    error: bool, // false = aviso
+    category: &'static str,
+    message: String,
+}
+
+/// Opciones de línea de comandos; quien integre el motor parte de `Options::default()`.
+#[derive(Debug, Default)]
+pub struct Options {
+    width: Option<usize>,  // --width <n>
+    debug: bool,           // --debug: comandos de autor
+    verbose: bool,         // --verbose: traza del despacho de comandos por stderr
+    world: Option<String>, // --world <ruta.json>
+    auto_reverse: bool,    // --auto-reverse
+    no_pager: bool,        // --no-pager
+    no_menu: bool,         // --no-menu
+    no_nudge: bool,        // --no-nudge
+    transcript: Option<String>, // --transcript <ruta>
+    lang: &'static str,         // $MUD_LANG, uno de `LANGUAGES`
+    suggestions: Option<bool>,  // --suggestions on|off
+    status: bool,          // --status
+    list: bool,            // subcomando `list`
+    validate: Option<String>, // subcomando `validate <mundo.json>`
+    check_save: Option<String>, // subcomando `check-save <partida.json>`
+    bench_load: Option<String>, // subcomando `bench-load <dir>`
+    save_info: Option<String>,  // subcomando `save-info <partida.json>`
+    autosave_on: Vec<String>,   // --autosave-on <eventos>, de `AUTOSAVE_EVENTS`
+    demo: Option<String>,       // --demo <guion.txt>
+    delay: Option<Duration>,    // --delay <ms>, pausa entre órdenes de la demostración
+    allow_solution: bool,  // --allow-solution
+    shuffle: bool,         // --shuffle
+    seed: Option<u64>,     // --seed <n>
+    difficulty: Option<Level>, // --difficulty <easy|normal|hard>
+    turn_limit: Option<u64>,   // --turn-limit <n>
+    save_dir: Option<String>,  // $MUD_SAVE_DIR
+    profile: Option<String>,   // --profile <nombre>
+}
+
+/// Idiomas que puede elegir `MUD_LANG`. Los textos están en español; en inglés de
+/// momento solo cambian las enumeraciones (ver `join_list`).
+const LANGUAGES: &[&str] = &["es", "en"];
+
+/// Enumeración natural: "a", "a y b", "a, b y c" ("e" ante "i-"/"hi-", como en
+/// "agua e hilo"); en inglés, "a, b, and c".
+fn join_list<S: AsRef<str>>(items: &[S], lang: &str) -> String {
+    let Some((last, init)) = items.split_last() else {
+        return String::new();
+    };
+    let last = last.as_ref();
+    if init.is_empty() {
+        return last.to_string();
+    }
+    let conj = match lang {
+        "en" if init.len() > 1 => ", and",
+        "en" => " and",
+        _ => {
+            let w = last.to_lowercase();
+            let i_sound = w.starts_with(['i', 'í'])
+                || (w.starts_with("hi") && !w[2..].starts_with(['a', 'e', 'o', 'u']));
+            if i_sound { " e" } else { " y" }
+        }
+    };
+    let init: Vec<&str> = init.iter().map(AsRef::as_ref).collect();
+    format!("{}{conj} {last}", init.join(", "))
+}
+
+/// Pausa de `--delay`: "1500ms", "1500" (milisegundos) o "2s".
+fn parse_delay(text: &str) -> Option<Duration> {
+    if let Some(ms) = text.strip_suffix("ms") {
+        return ms.parse().ok().map(Duration::from_millis);
+    }
+    if let Some(secs) = text.strip_suffix('s') {
+        return secs.parse().ok().map(Duration::from_secs);
+    }
+    text.parse().ok().map(Duration::from_millis)
+}
+
+/// Variable de entorno no vacía.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+impl Options {
+    /// Precedencia: `--world` / `play <mundo>`, luego `$MUD_WORLD`, y si no hay ninguno
+    /// el mundo integrado. `$MUD_SAVE_DIR` cambia la carpeta de perfiles (`saves/`; una
+    /// ruta explícita en `save` / `load` manda). `$MUD_LANG` admite `es` y `en`.
+    fn from_args() -> Result<Self> {
+        Self::parse(std::env::args().skip(1), env_var)
+    }
+
+    /// `from_args` con los argumentos y el entorno (`env`, como `env_var`) a la vista.
+    fn parse(args: impl IntoIterator<Item = String>, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut opts = Options { lang: LANGUAGES[0], ..Options::default() };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--width" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--width requiere un número"))?;
+                    let w = v.parse().map_err(|_| anyhow!("Ancho no válido: {v}"))?;
+                    opts.width = Some(w);
+                }
+                "--debug" if cfg!(feature = "debug-cmds") => opts.debug = true,
+                "--debug" => return Err(anyhow!("Esta versión se compiló sin los comandos de depuración.")),
+                "--verbose" => opts.verbose = true,
+                "--auto-reverse" => opts.auto_reverse = true,
+                "--no-pager" => opts.no_pager = true,
+                "--no-menu" => opts.no_menu = true,
+                "--no-nudge" => opts.no_nudge = true,
+                "--status" => opts.status = true,
+                "--allow-solution" => opts.allow_solution = true,
+                "--shuffle" => opts.shuffle = true,
+                "--seed" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--seed requiere un número"))?;
+                    opts.seed = Some(v.parse().map_err(|_| anyhow!("Semilla no válida: {v}"))?);
+                }
+                "--autosave-on" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--autosave-on requiere eventos (enter, goal, defeat)"))?;
+                    for event in v.split(',').map(str::trim) {
+                        if !AUTOSAVE_EVENTS.contains(&event) {
+                            return Err(anyhow!("Evento de autoguardado no válido: {event}"));
+                        }
+                        opts.autosave_on.push(event.to_string());
+                    }
+                }
+                "--demo" => {
+                    opts.demo = Some(args.next().ok_or_else(|| anyhow!("--demo requiere un guion"))?);
+                }
+                "--delay" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--delay requiere milisegundos"))?;
+                    opts.delay = Some(parse_delay(&v).ok_or_else(|| anyhow!("Pausa no válida: {v}"))?);
+                }
+                "--difficulty" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--difficulty requiere easy, normal o hard"))?;
+                    opts.difficulty = Some(Level::parse(&v).ok_or_else(|| anyhow!("Dificultad no válida: {v}"))?);
+                }
+                "--turn-limit" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--turn-limit requiere un número"))?;
+                    opts.turn_limit = Some(v.parse().map_err(|_| anyhow!("Límite de turnos no válido: {v}"))?);
+                }
+                "--profile" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--profile requiere un nombre"))?;
+                    if !valid_name(&v) {
+                        return Err(anyhow!("Nombre de perfil no válido: {v}"));
+                    }
+                    opts.profile = Some(v);
+                }
+                "--suggestions" => {
+                    let v = args.next().ok_or_else(|| anyhow!("--suggestions requiere on u off"))?;
+                    opts.suggestions = Some(match v.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(anyhow!("--suggestions requiere on u off, no {v}")),
+                    });
+                }
+                "--transcript" => {
+                    opts.transcript = Some(args.next().ok_or_else(|| anyhow!("--transcript requiere una ruta"))?);
+                }
+                "--world" => {
+                    opts.world = Some(args.next().ok_or_else(|| anyhow!("--world requiere una ruta"))?);
+                }
+                "list" => opts.list = true,
+                "save-info" => {
+                    opts.save_info = Some(args.next().ok_or_else(|| anyhow!("Uso: save-info <partida.json>"))?);
+                }
+                "bench-load" => {
+                    opts.bench_load = Some(args.next().ok_or_else(|| anyhow!("Uso: bench-load <dir>"))?);
+                }
+                // la ruta de la partida puede ir antes o después de `--world`
+                "check-save" | "--check-save" => opts.check_save = Some(String::new()),
+                path if opts.check_save.as_deref() == Some("") && !path.starts_with("--") => {
+                    opts.check_save = Some(path.to_string());
+                }
+                "validate" => {
+                    opts.validate = Some(args.next().ok_or_else(|| anyhow!("Uso: validate <mundo.json>"))?);
+                }
+                "play" => {
+                    let name = args.next().ok_or_else(|| anyhow!("Uso: play <mundo>"))?;
+                    opts.world = Some(world_path(&name));
+                }
+                other => return Err(anyhow!("Opción desconocida: {other}")),
+            }
+        }
+        if opts.check_save.as_deref() == Some("") {
+            return Err(anyhow!("Uso: check-save [--world <mundo.json>] <partida.json>"));
+        }
+        if opts.world.is_none() {
+            opts.world = env("MUD_WORLD");
+        }
+        opts.save_dir = env("MUD_SAVE_DIR");
+        if let Some(lang) = env("MUD_LANG") {
+            let code = lang.split(['_', '.']).next().unwrap_or_default().to_lowercase();
+            match LANGUAGES.iter().find(|l| **l == code) {
+                Some(l) => opts.lang = l,
+                None => eprintln!("Aviso: idioma {lang} no disponible; se usa es."),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// Gancho para quien integre el motor (p. ej. una interfaz gráfica) y quiera reaccionar
+/// a los cambios de estado sin analizar la salida de texto. Todos los métodos tienen
+/// una implementación vacía por defecto; los parámetros son claves, no nombres visibles.
+trait GameObserver: std::fmt::Debug {
+    /// El jugador pasa de la sala `from` a la sala `to` (comando `go`).
+    fn on_room_change(&mut self, _from: &str, _to: &str) {}
+    /// El jugador recoge el objeto `item` del suelo de la sala actual.
+    fn on_item_taken(&mut self, _item: &str) {}
+    /// El jugador suelta el objeto `item` en la sala actual (también con `drop all`).
+    fn on_item_dropped(&mut self, _item: &str) {}
+    /// El jugador mete `item` en el recipiente `container` (`put`, `drop ... in`).
+    fn on_item_put(&mut self, _item: &str, _container: &str) {}
+    /// `apply`: la herramienta `tool` convierte el objeto `from` en `to`.
+    fn on_item_transformed(&mut self, _tool: &str, _from: &str, _to: &str) {}
+    /// El jugador compra `item` por `price` monedas.
+    fn on_item_bought(&mut self, _item: &str, _price: u32) {}
+    /// El jugador vende `item` por `price` monedas.
+    fn on_item_sold(&mut self, _item: &str, _price: u32) {}
+    /// El jugador muere en la sala `room`.
+    fn on_death(&mut self, _room: &str) {}
+}
+
+/// Observador que ignora todos los eventos; es el que usa el binario.
+#[derive(Debug)]
+struct NoopObserver;
+
+impl GameObserver for NoopObserver {}
+
+#[derive(Debug)]
+pub struct Game {
+    world: World,
+    pristine: World, // el mundo tal como se cargó, para reinicios
+    player: Player,
+    running: bool,
+    ended: bool, // partida terminada: solo quedan los `META_VERBS`
+    outcome: Outcome,
+    turns: u64,
+    turn_limit: Option<u64>, // la partida acaba al llegar a este turno
+    last_save_turn: u64,     // turno del último guardado con éxito
+    show_status: bool, // línea de estado antes del prompt
+    width: usize,                // columnas para el ajuste de texto
+    page_height: Option<usize>, // filas por página; None = sin paginar
+    debug: bool,
+    verbose: bool, // traza de cada comando por stderr
+    lang: &'static str,
+    suggestions: bool, // "¿Quisiste decir...?" ante nombres mal escritos
+    nudges: bool,  // sin --no-nudge
+    failed: Option<&'static str>, // pista suave si el comando de este turno ha fallado
+    failures: Vec<String>,        // últimos comandos fallidos, el más reciente al final
+    allow_solution: bool,
+    assisted: bool, // se ha consultado la solución en esta partida
+    difficulty: Difficulty,
+    store: Box<dyn RoomStore>,
+    observer: Box<dyn GameObserver>,
+    save_dir: Option<String>, // $MUD_SAVE_DIR
+    profile: String,
+    checkpoints: IndexMap<String, SaveData>, // `checkpoint` / `rewind`; solo en memoria
+    autosave_on: Vec<String>, // eventos que guardan la partida (--autosave-on)
+    autosave_pending: bool,   // ha habido uno en este comando
+    demo: bool,               // en --demo no se apuntan puntuaciones ni se autoguarda
+    seed: Option<u64>, // semilla del azar de la partida (--shuffle); None si no hay azar
+    replies: Option<std::collections::VecDeque<String>>, // entrada preparada (órdenes y respuestas a `ask`), en vez de la terminal
+}
+
+impl Game {
+    /// Partida nueva en `world`.
+    pub fn new(world: World, opts: Options) -> Self {
+        let difficulty = Difficulty::resolve(&world, opts.difficulty);
+        let turn_limit = opts.turn_limit.or(world.turn_limit);
+        let world_dir = world.room_dir.clone();
+        Self {
+            pristine: world.clone(),
+            player: Player::new(&world, &difficulty),
+            world,
+            running: true,
+            ended: false,
+            outcome: Outcome::Quit,
+            turns: 0,
+            turn_limit,
+            last_save_turn: 0,
+            show_status: opts.status,
+            width: resolve_width(opts.width),
+            page_height: if opts.no_pager { None } else { terminal_height() },
+            debug: opts.debug,
+            verbose: opts.verbose,
+            lang: opts.lang,
+            suggestions: opts.suggestions.unwrap_or(true),
+            nudges: !opts.no_nudge,
+            failed: None,
+            failures: vec![],
+            allow_solution: opts.allow_solution,
+            assisted: false,
+            difficulty,
+            store: match &world_dir {
+                Some(dir) => Box::new(LazyDirStore { dir: dir.clone() }),
+                None => Box::new(MemoryStore),
+            },
+            observer: Box::new(NoopObserver),
+            save_dir: opts.save_dir,
+            profile: opts.profile.unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+            checkpoints: IndexMap::new(),
+            autosave_on: opts.autosave_on,
+            autosave_pending: false,
+            demo: false,
+            seed: if opts.shuffle { opts.seed } else { None },
+            replies: None,
+        }
+    }
+
+    /// Cambia el observador de eventos (por defecto, `NoopObserver`). El binario no lo
+    /// usa; es para quien integre el motor.
+    #[allow(dead_code)]
+    fn set_observer(&mut self, observer: Box<dyn GameObserver>) {
+        self.observer = observer;
+    }
+
+    /// Trae a memoria la sala `key` si aún no lo está. `false` si no existe; error si
+    /// existe pero no se puede leer.
+    fn ensure_room(&mut self, key: &str) -> Result<bool, GameError> {
+        if self.world.rooms.contains_key(key) {
+            return Ok(true);
+        }
+        let Some(room) = self.store.fetch(key)? else {
+            return Ok(false);
+        };
+        self.pristine.rooms.insert(key.to_string(), room.clone());
+        self.pristine.instantiate_room(key);
+        self.world.rooms.insert(key.to_string(), room);
+        self.world.instantiate_room(key);
+        Ok(true)
+    }
+
+    fn print_wrapped(&self, text: &str) {
+        self.print_paged(&wrap_text(text, self.width));
+    }
+
+    /// Imprime `lines` de página en página si no caben en la terminal.
+    fn print_paged<S: AsRef<str>>(&self, lines: &[S]) {
+        // una fila se reserva para el aviso de "más"
+        let per_page = match self.page_height {
+            Some(h) if h > 1 && lines.len() > h - 1 => h - 1,
+            _ => {
+                for line in lines {
+                    println!("{}", line.as_ref());
+                }
+                return;
+            }
+        };
+        for (i, chunk) in lines.chunks(per_page).enumerate() {
+            if i > 0 {
+                print!("-- más -- (Enter para continuar)");
+                io::stdout().flush().ok();
+                let mut buf = String::new();
+                if io::stdin().read_line(&mut buf).is_err() {
+                    return;
+                }
+            }
+            for line in chunk {
+                println!("{}", line.as_ref());
+            }
+        }
+    }
+
+    fn current_room(&self) -> &Room {
+        self.world.rooms.get(&self.player.location).expect("room not found")
+    }
+
+    fn current_room_mut(&mut self) -> &mut Room {
+        self.world.rooms.get_mut(&self.player.location).expect("room not found")
+    }
+
+    /// Objeto visible con las luces que lleva el jugador (ver `visible_under`).
+    fn is_revealed(&self, item: &Item) -> bool {
+        let Some(needed) = &item.visible_under else {
+            return true;
+        };
+        self.player
+            .inventory
+            .iter()
+            .filter_map(|k| self.world.items.get(k))
+            .any(|it| it.gives_light() && it.effects.get("reveals") == Some(needed))
+    }
+
+    /// Claves de los objetos del suelo que se pueden percibir (sin contar la oscuridad).
+    fn items_here(&self) -> Vec<String> {
+        self.current_room()
+            .items
+            .iter()
+            .filter(|k| self.world.items.get(*k).is_some_and(|it| self.is_revealed(it)))
+            .cloned()
+            .collect()
+    }
+
+    fn find_item_here(&self, token: &str) -> Option<String> {
+        let token = token.to_lowercase();
+        for key in &self.items_here() {
+            if let Some(it) = self.world.items.get(key) {
+                if it.answers_to(&token) {
+                    return Some(it.key.clone());
+                }
+            }
+        }
+        None
+    }
+
+    fn find_npc_here(&self, token: &str) -> Option<&Npc> {
+        let token = token.to_lowercase();
+        let here = &self.player.location;
+        self.world.npcs.values().find(|n| &n.location == here && n.matches(&token))
+    }
+
+    fn find_item_inventory(&self, token: &str) -> Option<String> {
+        let token = token.to_lowercase();
+        for key in &self.player.inventory {
+            if let Some(it) = self.world.items.get(key) {
+                if it.answers_to(&token) {
+                    return Some(it.key.clone());
+                }
+            }
+        }
+        None
+    }
+
+    /// Nombre del objeto entre `keys` más parecido a `token`, si está lo bastante cerca
+    /// como para sugerirlo sin riesgo de adivinar mal.
+    fn suggest_item<'a>(&self, token: &str, keys: impl Iterator<Item = &'a String>) -> Option<String> {
+        if !self.suggestions {
+            return None;
+        }
+        let token = token.to_lowercase();
+        let max = match token.chars().count() {
+            0..=3 => return None,
+            4..=6 => 1,
+            _ => 2,
+        };
+        keys.filter_map(|k| self.world.items.get(k))
+            .filter_map(|it| {
+                let d = edit_distance(&token, &it.key.to_lowercase())
+                    .min(edit_distance(&token, &it.name.to_lowercase()));
+                (d <= max).then_some((d, it.name.clone()))
+            })
+            .min_by_key(|(d, _)| *d)
+            .map(|(_, name)| name)
+    }
+
+    fn report_not_here(&self, token: &str) {
+        match self.suggest_item(token, self.items_here().iter()) {
+            Some(name) => println!("¿Quisiste decir '{name}'?"),
+            None => println!("No ves eso aquí."),
+        }
+    }
+
+    fn report_not_carried(&self, token: &str) {
+        match self.suggest_item(token, self.player.inventory.iter()) {
+            Some(name) => println!("¿Quisiste decir '{name}'?"),
+            None => println!("No llevas eso."),
+        }
+    }
+
+    /// Lleva algún objeto con la etiqueta `tag`.
+    fn carries_tag(&self, tag: &str) -> bool {
+        self.player
+            .inventory
+            .iter()
+            .any(|k| self.world.items.get(k).is_some_and(|it| it.tags.iter().any(|t| t == tag)))
+    }
+
+    /// Lleva `key` o un ejemplar suyo.
+    fn carries(&self, key: &str) -> bool {
+        self.player
+            .inventory
+            .iter()
+            .any(|k| k == key || self.world.items.get(k).is_some_and(|it| it.proto() == key))
+    }
+
+    /// Lleva una luz encendida, en la mano o dentro de un recipiente abierto que no
+    /// la tape (`suppresses_light`).
+    fn has_light(&self) -> bool {
+        self.player.inventory.iter().any(|k| self.shines(k, 0))
+    }
+
+    /// `key` da luz o la deja pasar desde su contenido; `depth` corta los ciclos.
+    fn shines(&self, key: &str, depth: usize) -> bool {
+        let Some(it) = self.world.items.get(key) else {
+            return false;
+        };
+        it.gives_light()
+            || (it.container
+                && !it.closed
+                && !it.suppresses_light
+                && depth < self.world.items.len()
+                && it.contents.iter().any(|k| self.shines(k, depth + 1)))
+    }
+
+    /// La sala actual es oscura y el jugador no lleva luz.
+    fn in_darkness(&self) -> bool {
+        *self.current_room().flags.get("dark").unwrap_or(&false) && !self.has_light()
+    }
+
+    /// "Ves aquí: ...", o solo cuántos hay si pasan de `look_item_limit` (por defecto
+    /// `LOOK_ITEM_LIMIT`); `names` los lista todos.
+    fn items_line(&self, names: &[String]) -> String {
+        if names.len() > self.world.look_item_limit.unwrap_or(LOOK_ITEM_LIMIT) {
+            format!("Ves aquí: {} objetos (escribe 'names' para verlos).", names.len())
+        } else {
+            format!("Ves aquí: {}", join_list(names, self.lang))
+        }
+    }
+
+    fn cmd_look(&self) {
+        let room = self.current_room();
+
+        if self.in_darkness() {
+            println!("Está muy oscuro. Apenas distingues siluetas.");
+            println!("{}", exits_line(&self.world, room));
+            room.print_sounds();
+            return;
+        }
+
+        println!("\n{}", room.name);
+        println!("{}", "-".repeat(room.name.len()));
+        self.print_wrapped(&room.desc);
+
+        let here = self.items_here();
+        if !here.is_empty() {
+            let names: Vec<String> = here
+                .iter()
+                .filter_map(|k| self.world.items.get(k).map(Item::listed_name))
+                .collect();
+            println!("\n{}", self.items_line(&names));
+        }
+
+        let npcs: Vec<String> = self
+            .world
+            .npcs
+            .values()
+            .filter(|n| n.location == room.key)
+            .map(|n| n.name.clone())
+            .collect();
+        if !npcs.is_empty() {
+            println!("Está aquí: {}", join_list(&npcs, self.lang));
+        }
+
+        println!("{}", exits_line(&self.world, room));
+        room.print_sounds();
+    }
+
+    /// `feel`: a oscuras se palpan las salidas y los objetos fijos (no los pequeños);
+    /// con luz es un `look` breve sin la descripción.
+    fn cmd_feel(&self) {
+        let room = self.current_room();
+        if self.in_darkness() {
+            let fixed: Vec<String> = self
+                .items_here()
+                .iter()
+                .filter_map(|k| self.world.items.get(k))
+                .filter(|it| !it.portable)
+                .map(|it| it.a_name())
+                .collect();
+            if fixed.is_empty() {
+                println!("Palpas a tu alrededor, pero solo encuentras roca.");
+            } else {
+                println!("Palpas {}.", join_list(&fixed, self.lang));
+            }
+            println!("{}", exits_line(&self.world, room));
+            return;
+        }
+        println!("{}", room.name);
+        let names: Vec<String> = self
+            .items_here()
+            .iter()
+            .filter_map(|k| self.world.items.get(k).map(Item::listed_name))
+            .collect();
+        if !names.is_empty() {
+            println!("{}", self.items_line(&names));
+        }
+        println!("{}", exits_line(&self.world, room));
+    }
+
+    fn cmd_exits(&self) {
+        println!("{}", exits_line(&self.world, self.current_room()));
+    }
+
+    /// `go <dir> [veces]`: con un número avanza varias salas seguidas en la misma
+    /// dirección, parándose si una salida falta o está bloqueada.
+    fn cmd_go(&mut self, dir: Option<&str>, count: Option<&str>) -> bool {
+        let Some(direction) = dir.map(|d| self.world.canonical_dir(d)) else {
+            println!("Uso: go <north|south|east|west|up|down> [veces]");
+            return false;
+        };
+        let steps = match count.map(str::parse::<usize>) {
+            None => 1,
+            Some(Ok(n)) if n > 0 => n.min(MAX_GO_STEPS),
+            Some(_) => {
+                println!("Uso: go <dir> [veces]");
+                return false;
+            }
+        };
+        for i in 0..steps {
+            if i > 0 {
+                // cada sala extra cuenta como un turno; el último lo cierra el bucle
+                self.end_turn();
+                if !self.in_play() {
+                    return true;
+                }
+            }
+            if !self.step(&direction) {
+                // los tramos ya recorridos han gastado su turno; el fallido, no
+                if i > 0 {
+                    self.cmd_look();
+                }
+                return false;
+            }
+            if i + 1 < steps {
+                println!("Pasas por {}.", self.current_room().name);
+            }
+        }
+        self.cmd_look();
+        true
+    }
+
+    /// `climb`, `enter` y `exit`: `go` por la salida que les toca en esta sala. `climb`
+    /// sube (`up`); `enter` usa `in` o, si no la hay, la única salida que no es de las
+    /// de siempre; `exit` usa `out` o, en su defecto, `down`.
+    fn cmd_go_verb(&mut self, verb: &str) -> bool {
+        let exits = &self.current_room().exits;
+        let (dir, missing) = match verb {
+            "climb" => (exits.contains_key("up").then(|| "up".to_string()), "No puedes trepar aquí."),
+            "enter" => {
+                let interior = || {
+                    let mut odd = exits.keys().filter(|d| opposite_dir(d).is_none() && *d != "out");
+                    match (odd.next(), odd.next()) {
+                        (Some(d), None) => Some(d.clone()),
+                        _ => None,
+                    }
+                };
+                let dir = if exits.contains_key("in") { Some("in".to_string()) } else { interior() };
+                (dir, "No ves por dónde entrar.")
+            }
+            _ => (
+                ["out", "down"].into_iter().find(|d| exits.contains_key(*d)).map(String::from),
+                "No ves por dónde salir.",
+            ),
+        };
+        match dir {
+            Some(dir) => self.cmd_go(Some(&dir), None),
+            None => {
+                println!("{missing}");
+                self.failed = Some("Prueba con 'exits' para ver por dónde se puede ir.");
+                false
+            }
+        }
+    }
+
+    /// Un paso en `direction`. Devuelve `false` (tras explicar por qué) si no se puede.
+    fn step(&mut self, direction: &str) -> bool {
+        if self.difficulty.dark_blocks_movement && self.in_darkness() {
+            println!("No te atreves a avanzar a ciegas.");
+            self.failed = Some("Quizás necesites una luz.");
+            return false;
+        }
+
+        let cur = self.current_room().clone();
+        let Some(dest) = cur.exits.get(direction) else {
+            println!("No hay salida en esa dirección.");
+            self.failed = Some("Prueba con 'exits' para ver por dónde se puede ir.");
+            return false;
+        };
+        match self.ensure_room(dest) {
+            Ok(true) => {}
+            Ok(false) => {
+                println!("Esa salida no lleva a ninguna parte.");
+                return false;
+            }
+            Err(e) => {
+                println!("{e}");
+                return false;
+            }
+        }
+
+        if let Some(req) = cur.requires.get(direction) {
+            if !self.carries(&req.item) {
+                match &req.message {
+                    Some(msg) => println!("{msg}"),
+                    None => match self.world.items.get(&req.item) {
+                        Some(it) => println!("Necesitas {} para pasar.", it.a_name()),
+                        None => println!("Algo te impide pasar."),
+                    },
+                }
+                self.failed = Some(NUDGE_LOCKED);
+                return false;
+            }
+        }
+
+        // bloqueo por bandera: locked_<dir>
+        let flag = format!("locked_{direction}");
+        if *cur.flags.get(&flag).unwrap_or(&false) {
+            // ¿tiene llave?
+            if self.key_for(&cur.key, direction).is_none() {
+                println!("La salida está bloqueada.");
+                self.failed = Some(NUDGE_LOCKED);
+                return false;
+            }
+            // desbloquear
+            if let Some(r) = self.world.rooms.get_mut(&cur.key) {
+                r.flags.insert(flag.clone(), false);
+            }
+            println!("Usas la llave y desbloqueas la salida.");
+        }
+
+        let exit = format!("{}:{direction}", cur.key);
+        if !self.player.crossed.contains(&exit) {
+            if let Some(msg) = cur.first_cross_message.get(direction) {
+                self.print_wrapped(msg);
+            }
+            self.player.crossed.push(exit);
+        }
+        self.player.location = dest.clone();
+        if !self.player.visited.contains(dest) {
+            self.player.visited.push(dest.clone());
+            self.autosave_event("enter");
+        }
+        self.observer.on_room_change(&cur.key, dest);
+        true
+    }
+
+    /// `travel <sala>`: camina hasta una sala ya visitada por la ruta conocida más corta
+    /// sin salidas bloqueadas, parándose si algo lo impide por el camino.
+    fn cmd_travel(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: travel <sala>");
+            return false;
+        };
+        let dest = self.player.visited.iter().find(|k| {
+            self.world
+                .rooms
+                .get(k.as_str())
+                .is_some_and(|room| same_words(&room.name, token) || k.eq_ignore_ascii_case(token))
+        });
+        let Some(dest) = dest.cloned() else {
+            println!("No conoces ningún lugar con ese nombre.");
+            return false;
+        };
+        let route = self.world.find_path(&self.player.location, &dest, true, Some(&self.player.visited));
+        let Some(dirs) = route else {
+            println!("No conoces un camino despejado hasta allí.");
+            return false;
+        };
+        if dirs.is_empty() {
+            println!("Ya estás ahí.");
+            return false;
+        }
+        for (i, dir) in dirs.iter().enumerate() {
+            if i > 0 {
+                self.end_turn();
+                if !self.in_play() {
+                    return true;
+                }
+            }
+            if !self.step(dir) {
+                println!("El viaje se interrumpe.");
+                self.cmd_look();
+                return false;
+            }
+        }
+        println!("Viajas a {}.", self.world.rooms[&dest].name);
+        self.cmd_look();
+        true
+    }
+
+    /// Llave del inventario cuyo `unlocks` apunta a `room:dir`.
+    fn key_for(&self, room: &str, dir: &str) -> Option<String> {
+        let target = format!("{room}:{dir}");
+        self.player
+            .inventory
+            .iter()
+            .find(|k| {
+                self.world
+                    .items
+                    .get(*k)
+                    .and_then(|it| it.effects.get("unlocks"))
+                    .is_some_and(|v| v == &target)
+            })
+            .cloned()
+    }
+
+    /// `lock <dir>` / `unlock <dir>`: echa o quita la llave de una salida de la sala actual.
+    fn cmd_lock(&mut self, dir: Option<&str>, lock: bool) -> bool {
+        let Some(direction) = dir.map(|d| self.world.canonical_dir(d)) else {
+            println!("Uso: {} <dir>", if lock { "lock" } else { "unlock" });
+            return false;
+        };
+        let room = self.current_room();
+        if !room.exits.contains_key(&direction) {
+            println!("No hay salida en esa dirección.");
+            return false;
+        }
+        let flag = format!("locked_{direction}");
+        let locked = room.flags.get(&flag).copied().unwrap_or(false);
+        if locked == lock {
+            println!("{}", if lock { "Ya está cerrada con llave." } else { "No está cerrada con llave." });
+            return false;
+        }
+        let Some(key) = self.key_for(&room.key, &direction) else {
+            println!("No tienes la llave.");
+            return false;
+        };
+        let name = self.world.items[&key].the_name();
+        let label = self.world.dir_label(&direction).to_string();
+        self.current_room_mut().flags.insert(flag, lock);
+        if lock {
+            println!("Cierras con {name}: la salida {label} queda bloqueada.");
+        } else {
+            println!("Usas {name} y desbloqueas la salida {label}.");
+        }
+        true
+    }
+
+    fn cmd_take(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: take <objeto>");
+            return false;
+        };
+        let Some(key) = self.find_item_here(token) else {
+            self.report_not_here(token);
+            return false;
+        };
+        let portable = self
+            .world
+            .items
+            .get(&key)
+            .map(|i| i.portable)
+            .unwrap_or(false);
+        if !portable {
+            println!("No puedes cargar eso.");
+            return false;
+        }
+        if self.inventory_full() {
+            self.report_full();
+            return self.make_room() && self.cmd_take(Some(token));
+        }
+        let room = self.current_room_mut();
+        if let Some(idx) = room.items.iter().position(|k| k == &key) {
+            room.items.remove(idx);
+        }
+        self.player.inventory.push(key.clone());
+        self.observer.on_item_taken(&key);
+        println!("Tomaste {}.", self.world.items[&key].the_name());
+        true
+    }
+
+    /// Recipiente abierto llamado `tok`, llevado o en la sala; si no, explica por qué.
+    fn open_container(&self, tok: &str) -> Option<String> {
+        let Some(ckey) = self.find_item_inventory(tok).or_else(|| self.find_item_here(tok)) else {
+            self.report_not_here(tok);
+            return None;
+        };
+        let cont = &self.world.items[&ckey];
+        if !cont.container {
+            println!("{} no es un recipiente.", capitalize(&cont.the_name()));
+            return None;
+        }
+        if cont.closed {
+            println!("{} está cerrado.", capitalize(&cont.the_name()));
+            return None;
+        }
+        Some(ckey)
+    }
+
+    fn report_full(&self) {
+        println!("{}", self.world.full_message.as_deref().unwrap_or("No puedes llevar más cosas."));
+    }
+
+    /// Se puede preguntar al jugador: hay respuestas preparadas o una terminal.
+    fn can_ask(&self) -> bool {
+        self.replies.is_some() || (io::stdin().is_terminal() && io::stdout().is_terminal())
+    }
+
+    /// Hace una pregunta y devuelve la respuesta: la siguiente de `replies` si las hay
+    /// y, si no, la que se teclee. Sin terminal no se pregunta (`None`).
+    fn ask(&mut self, question: &str) -> Option<String> {
+        if !self.can_ask() {
+            return None;
+        }
+        print!("{question} ");
+        io::stdout().flush().ok();
+        let mut buf = String::new();
+        match &mut self.replies {
+            Some(replies) => buf = replies.pop_front().unwrap_or_default(),
+            None => {
+                io::stdin().read_line(&mut buf).ok();
+            }
+        }
+        Some(buf)
+    }
+
+    /// Con el inventario lleno, en una sesión interactiva ofrece soltar algo. Devuelve
+    /// `true` si ha quedado sitio. Sin terminal no pregunta.
+    fn make_room(&mut self) -> bool {
+        if !self.can_ask() {
+            return false;
+        }
+        self.cmd_inventory();
+        let Some(reply) = self.ask("¿Qué quieres soltar para hacer espacio? (o 'nada')") else {
+            return false;
+        };
+        let choice = normalize_input(reply.trim());
+        if choice.is_empty() || choice.eq_ignore_ascii_case("nada") {
+            println!("Te quedas como estás.");
+            return false;
+        }
+        if self.find_item_inventory(&choice).is_none() {
+            self.report_not_carried(&choice);
+            return false;
+        }
+        self.cmd_drop(Some(&choice));
+        !self.inventory_full()
+    }
+
+    /// `take <objeto> from <recipiente>`: saca un objeto de un recipiente abierto,
+    /// esté en la sala o en el inventario.
+    fn cmd_take_from(&mut self, item_tok: &str, cont_tok: &str) -> bool {
+        let Some(ckey) = self.open_container(cont_tok) else {
+            return false;
+        };
+        let cont = &self.world.items[&ckey];
+        let token = item_tok.to_lowercase();
+        let found = cont.contents.iter().find(|k| {
+            self.world
+                .items
+                .get(*k)
+                .is_some_and(|it| it.answers_to(&token))
+        });
+        let Some(key) = found.cloned() else {
+            println!("{} no contiene eso.", capitalize(&cont.the_name()));
+            return false;
+        };
+        if !self.world.items[&key].portable {
+            println!("No puedes cargar eso.");
+            return false;
+        }
+        if self.inventory_full() {
+            self.report_full();
+            return false;
+        }
+        if let Some(c) = self.world.items.get_mut(&ckey) {
+            c.contents.retain(|k| k != &key);
+        }
+        self.player.inventory.push(key.clone());
+        self.observer.on_item_taken(&key);
+        println!("Sacas {} {}.", self.world.items[&key].the_name(), self.world.items[&ckey].of_name());
+        true
+    }
+
+    /// `put <objeto> in <recipiente>` (o `drop ... in ...`): del inventario al recipiente.
+    fn cmd_put(&mut self, item_tok: &str, cont_tok: &str) -> bool {
+        let Some(key) = self.find_item_inventory(item_tok) else {
+            self.report_not_carried(item_tok);
+            return false;
+        };
+        let Some(ckey) = self.open_container(cont_tok) else {
+            return false;
+        };
+        let cont = &self.world.items[&ckey];
+        if key == ckey {
+            println!("No puedes meter algo dentro de sí mismo.");
+            return false;
+        }
+        if cont.max_contents.is_some_and(|max| cont.contents.len() >= max) {
+            println!("{} está lleno.", capitalize(&cont.the_name()));
+            return false;
+        }
+        self.player.inventory.retain(|k| k != &key);
+        if let Some(c) = self.world.items.get_mut(&ckey) {
+            c.contents.push(key.clone());
+        }
+        self.observer.on_item_put(&key, &ckey);
+        println!("Metes {} en {}.", self.world.items[&key].the_name(), self.world.items[&ckey].the_name());
+        true
+    }
+
+    /// `apply <herramienta> to <objeto>`: la herramienta, en el inventario, convierte el
+    /// objeto (del suelo o del inventario) en el que indique su `transforms`, en su sitio.
+    fn cmd_apply(&mut self, tool_tok: &str, target_tok: &str) -> bool {
+        let Some(tool) = self.find_item_inventory(tool_tok) else {
+            self.report_not_carried(tool_tok);
+            return false;
+        };
+        let carried = self.find_item_inventory(target_tok);
+        let Some(target) = carried.clone().or_else(|| self.find_item_here(target_tok)) else {
+            self.report_not_here(target_tok);
+            return false;
+        };
+        let result = self.world.items[&tool].transforms.get(self.world.items[&target].proto()).cloned();
+        let Some(result) = result.filter(|r| self.world.items.contains_key(r)) else {
+            println!("No sirve para eso.");
+            return false;
+        };
+        if let Some(line) = self.identify(&tool) {
+            println!("{line}");
+        }
+        let slots = if carried.is_some() {
+            &mut self.player.inventory
+        } else {
+            &mut self.current_room_mut().items
+        };
+        if let Some(slot) = slots.iter_mut().find(|k| **k == target) {
+            *slot = result.clone();
+        }
+        self.observer.on_item_transformed(&tool, &target, &result);
+        println!(
+            "Aplicas {} a {} y obtienes {}.",
+            self.world.items[&tool].the_name(),
+            self.world.items[&target].the_name(),
+            self.world.items[&result].the_name()
+        );
+        true
+    }
+
+    fn cmd_open(&mut self, tok: Option<&str>, open: bool) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: {} <recipiente>", if open { "open" } else { "close" });
+            return false;
+        };
+        let Some(key) = self.find_item_inventory(token).or_else(|| self.find_item_here(token)) else {
+            self.report_not_here(token);
+            return false;
+        };
+        let item = &self.world.items[&key];
+        if !item.container {
+            println!("No se puede {} eso.", if open { "abrir" } else { "cerrar" });
+            return false;
+        }
+        if item.closed != open {
+            println!("Ya está {}.", if open { "abierto" } else { "cerrado" });
+            return false;
+        }
+        let name = item.the_name();
+        let contents: Vec<String> = item
+            .contents
+            .iter()
+            .filter_map(|k| self.world.items.get(k).map(Item::listed_name))
+            .collect();
+        if let Some(it) = self.world.items.get_mut(&key) {
+            it.closed = !open;
+        }
+        if !open {
+            println!("Cierras {name}.");
+        } else if contents.is_empty() {
+            println!("Abres {name}. Está vacío.");
+        } else {
+            println!("Abres {name}. Dentro hay: {}", join_list(&contents, self.lang));
+        }
+        true
+    }
+
+    fn cmd_drop(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: drop <objeto>");
+            return false;
+        };
+        let room = self.current_room();
+        if *room.flags.get("no_drop").unwrap_or(&false) {
+            let msg = room.no_drop_message.as_deref().unwrap_or("No te atreves a dejar nada aquí.");
+            println!("{msg}");
+            return false;
+        }
+        if token.eq_ignore_ascii_case("all") || token.eq_ignore_ascii_case("todo") {
+            if self.player.inventory.is_empty() {
+                println!("No llevas nada.");
+                return false;
+            }
+            for key in std::mem::take(&mut self.player.inventory) {
+                self.current_room_mut().items.push(key.clone());
+                self.observer.on_item_dropped(&key);
+                println!("Dejaste {}.", self.world.items[&key].the_name());
+            }
+            return true;
+        }
+        let Some(key) = self.find_item_inventory(token) else {
+            self.report_not_carried(token);
+            return false;
+        };
+        if let Some(idx) = self.player.inventory.iter().position(|k| k == &key) {
+            self.player.inventory.remove(idx);
+        }
+        self.current_room_mut().items.push(key.clone());
+        self.observer.on_item_dropped(&key);
+        println!("Dejaste {}.", self.world.items[&key].the_name());
+        true
+    }
+
+    /// `look under <objeto>` / `look behind <objeto>`: la primera vez, lo escondido en
+    /// `under` / `behind` pasa al suelo de la sala.
+    fn cmd_look_hidden(&mut self, place: &str, token: &str) {
+        let prep = if place == "under" { "debajo" } else { "detrás" };
+        if token.is_empty() {
+            println!("Uso: look {place} <objeto>");
+            return;
+        }
+        if self.in_darkness() {
+            println!("Está demasiado oscuro para ver eso.");
+            return;
+        }
+        let Some(key) = self.find_item_here(token).or_else(|| self.find_item_inventory(token)) else {
+            self.report_not_here(token);
+            return;
+        };
+        let item = &self.world.items[&key];
+        let hidden = if place == "under" { &item.under } else { &item.behind };
+        if hidden.is_empty() || item.searched.iter().any(|p| p == place) {
+            println!("No hay nada {prep} {}.", item.of_name());
+            return;
+        }
+        let hidden = hidden.clone();
+        let names: Vec<String> = hidden.iter().filter_map(|k| self.world.items.get(k).map(|it| it.name.clone())).collect();
+        println!("{} {}: {}.", capitalize(prep), item.of_name(), join_list(&names, self.lang));
+        if let Some(it) = self.world.items.get_mut(&key) {
+            it.searched.push(place.to_string());
+        }
+        self.current_room_mut().items.extend(hidden);
+    }
+
+    fn cmd_examine(&mut self, tok: Option<&str>) {
+        let Some(token) = tok else {
+            println!("Uso: examine <objeto>");
+            return;
+        };
+        if matches!(token.to_lowercase().as_str(), "me" | "self" | "myself" | "yo") {
+            self.examine_self();
+            return;
+        }
+        if matches!(token.to_lowercase().as_str(), "inventory" | "inventario" | "inv" | "all" | "todo") {
+            self.examine_inventory();
+            return;
+        }
+        // a oscuras solo se puede examinar lo que se lleva encima
+        let here = if self.in_darkness() { None } else { self.find_item_here(token) };
+        let Some(key) = self.find_item_inventory(token).or(here) else {
+            if let Some(npc) = self.find_npc_here(token).filter(|_| !self.in_darkness()) {
+                self.print_wrapped(npc.current_desc(&self.current_room().flags));
+                return;
+            }
+            if self.in_darkness() {
+                println!("Está demasiado oscuro para ver eso.");
+            } else {
+                self.report_not_here(token);
+            }
+            return;
+        };
+        self.examine_item(&key);
+    }
+
+    /// `examine inventory` / `examine all`: nombre y descripción de cada objeto que se
+    /// lleva. Funciona a oscuras, como examinar uno solo de ellos.
+    fn examine_inventory(&mut self) {
+        if self.player.inventory.is_empty() {
+            println!("No llevas nada.");
+            return;
+        }
+        for (i, key) in self.player.inventory.clone().iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("{}:", capitalize(&self.world.items[key].name));
+            self.examine_item(key);
+        }
+    }
+
+    /// `describe_item`, salvo que el objeto ya se haya examinado las `examine_limit`
+    /// veces que permite (una visión que se desvanece).
+    fn examine_item(&mut self, key: &str) {
+        let Some(item) = self.world.items.get_mut(key) else {
+            return;
+        };
+        if item.examine_limit.is_some_and(|limit| item.examined >= limit) {
+            println!("Ya no distingues nada nuevo.");
+            return;
+        }
+        item.examined += 1;
+        self.describe_item(key);
+    }
+
+    /// Lo que muestra `examine` de un objeto: su descripción (la de misterio si no se ha
+    /// identificado) y, si se identificó, lo que se sabe de él.
+    fn describe_item(&self, key: &str) {
+        let item = &self.world.items[key];
+        if item.is_unidentified() {
+            self.print_wrapped(item.unidentified_desc.as_deref().unwrap_or_default());
+            return;
+        }
+        self.print_wrapped(item.current_desc());
+        if item.unidentified_desc.is_some() {
+            let mut known: Vec<String> = item.effects.iter().filter_map(|(k, v)| effect_label(k, v)).collect();
+            known.sort();
+            if !known.is_empty() {
+                println!("Sabes que {}.", known.join(", "));
+            }
+        }
+    }
+
+    /// `examine me`: nombre, estado de salud, ropa y luces que lleva el jugador.
+    fn examine_self(&self) {
+        let p = &self.player;
+        println!("Eres {}.", p.name);
+        if let Some(desc) = &p.desc {
+            self.print_wrapped(desc);
+        }
+        let condition = if p.health >= p.max_health {
+            "Estás en plena forma"
+        } else if p.health * 2 > p.max_health {
+            "Tienes algunos rasguños"
+        } else if p.health > LOW_HEALTH {
+            "Estás herido"
+        } else {
+            "Estás muy débil"
+        };
+        println!("{condition} ({}/{} de salud).", p.health, p.max_health);
+        let carried: Vec<&Item> = p.inventory.iter().filter_map(|k| self.world.items.get(k)).collect();
+        if carried.iter().any(|it| it.effects.contains_key("poison")) {
+            println!("Algo de lo que llevas te está envenenando.");
+        }
+        if let Some(it) = self.wielded().and_then(|k| self.world.items.get(k)) {
+            println!("Empuñas {}.", it.the_name());
+        }
+        let lights: Vec<&&Item> = carried.iter().filter(|it| it.gives_light()).collect();
+        if lights.is_empty() {
+            println!("No llevas ninguna luz encendida.");
+        }
+        for it in lights {
+            match it.fuel {
+                Some(f) => println!("{} arde; le quedan {f} turnos.", capitalize(&it.the_name())),
+                None => println!("{} ilumina a tu alrededor.", capitalize(&it.the_name())),
+            }
+        }
+    }
+
+    /// `read`: efecto `text` (su valor es el texto) o `readable` (se lee la descripción).
+    fn cmd_read(&self, tok: Option<&str>) {
+        let Some(token) = tok else {
+            println!("Uso: read <objeto>");
+            return;
+        };
+        let Some(key) = self.find_item_inventory(token).or_else(|| self.find_item_here(token)) else {
+            self.report_not_here(token);
+            return;
+        };
+        if self.in_darkness() {
+            println!("Está demasiado oscuro para leer.");
+            return;
+        }
+        let item = &self.world.items[&key];
+        if let Some(text) = item.effects.get("text") {
+            self.print_wrapped(text);
+        } else if item.effects.contains_key("readable") {
+            self.print_wrapped(item.current_desc());
+        } else {
+            println!("No hay nada que leer ahí.");
+        }
+    }
+
+    fn inventory_full(&self) -> bool {
+        self.player
+            .max_items
+            .is_some_and(|max| self.player.inventory.len() >= max)
+    }
+
+    /// Arma empuñada, si sigue en el inventario.
+    fn wielded(&self) -> Option<&String> {
+        self.player.wielded.as_ref().filter(|k| self.player.inventory.contains(k))
+    }
+
+    /// `wield <objeto>`: empuña un objeto del inventario con efecto `damage`.
+    fn cmd_wield(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: wield <objeto>");
+            return false;
+        };
+        let Some(key) = self.find_item_inventory(token) else {
+            self.report_not_carried(token);
+            return false;
+        };
+        let item = &self.world.items[&key];
+        if !item.effects.contains_key("damage") {
+            println!("Eso no sirve como arma.");
+            return false;
+        }
+        if self.wielded() == Some(&key) {
+            println!("Ya empuñas {}.", item.the_name());
+            return false;
+        }
+        println!("Empuñas {}.", item.the_name());
+        self.player.wielded = Some(key);
+        true
+    }
+
+    /// `attack <pnj>`: el arma empuñada (o los puños) le quita su `damage` de salud y,
+    /// si sigue en pie, devuelve el golpe con su `attack`. Al llegar a cero desaparece
+    /// y deja en el suelo lo que llevaba.
+    fn cmd_attack(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: attack <pnj>");
+            return false;
+        };
+        if self.in_darkness() {
+            println!("Está demasiado oscuro para ver eso.");
+            return false;
+        }
+        let Some(npc) = self.find_npc_here(token) else {
+            println!("No ves a nadie así aquí.");
+            return false;
+        };
+        let (nkey, name, attack) = (npc.key.clone(), npc.name.clone(), npc.attack);
+        let Some(health) = npc.health else {
+            println!("No tiene sentido pelear con {}.", npc.name);
+            return false;
+        };
+        let (weapon, damage) = match self.wielded().map(|k| &self.world.items[k]) {
+            Some(it) => (it.the_name(), it.effects.get("damage").and_then(|v| v.parse().ok()).unwrap_or(0)),
+            None => ("los puños".to_string(), FIST_DAMAGE),
+        };
+        let left = health.saturating_sub(damage);
+        println!("Golpeas con {weapon}: {name} pierde {damage} de salud.");
+        if left == 0 {
+            let npc = self.world.npcs.shift_remove(&nkey).expect("PNJ presente");
+            println!("{} cae y ya no se levanta.", capitalize(&name));
+            self.autosave_event("defeat");
+            let names: Vec<String> =
+                npc.items.iter().filter_map(|k| self.world.items.get(k).map(|it| it.name.clone())).collect();
+            if !names.is_empty() {
+                println!("Deja caer: {}.", join_list(&names, self.lang));
+            }
+            self.current_room_mut().items.extend(npc.items);
+            return true;
+        }
+        if let Some(n) = self.world.npcs.get_mut(&nkey) {
+            n.health = Some(left);
+        }
+        let hit = attack * self.difficulty.damage_percent / 100;
+        if hit == 0 {
+            return true;
+        }
+        self.player.health = self.player.health.saturating_sub(hit);
+        if self.player.health == 0 {
+            self.die(&format!("{} te devuelve el golpe, y es el último.", capitalize(&name)));
+        } else {
+            println!("{} te devuelve el golpe ({hit} de daño; te quedan {}).", capitalize(&name), self.player.health);
+        }
+        true
+    }
+
+    /// Inventario por secciones; las vacías no se muestran.
+    fn cmd_inventory(&self) {
+        if self.player.gold > 0 {
+            println!("Oro: {} monedas.", self.player.gold);
+        }
+        if self.player.inventory.is_empty() {
+            println!("No llevas nada.");
+            return;
+        }
+        if let Some(it) = self.wielded().and_then(|k| self.world.items.get(k)) {
+            println!("Empuñas: {}", it.name);
+        }
+        let carried: Vec<String> = self
+            .player
+            .inventory
+            .iter()
+            .filter_map(|k| self.world.items.get(k).map(Item::listed_name))
+            .collect();
+        let count = match self.player.max_items {
+            Some(max) => format!(" ({}/{max})", self.player.inventory.len()),
+            None => String::new(),
+        };
+        if !carried.is_empty() {
+            println!("Llevas{count}: {}", join_list(&carried, self.lang));
+        } else if !count.is_empty() {
+            println!("Objetos{count}.");
+        }
+    }
+
+    /// Primer comerciante en la sala actual.
+    fn shopkeeper_here(&self) -> Option<String> {
+        let here = &self.player.location;
+        self.world
+            .npcs
+            .values()
+            .find(|n| &n.location == here && n.is_shopkeeper())
+            .map(|n| n.key.clone())
+    }
+
+    /// `buy <objeto>`: compra al comerciante de la sala si el oro alcanza.
+    fn cmd_buy(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok.map(str::to_lowercase) else {
+            println!("Uso: buy <objeto>");
+            return false;
+        };
+        let Some(nkey) = self.shopkeeper_here() else {
+            println!("Aquí nadie vende nada.");
+            return false;
+        };
+        let npc = &self.world.npcs[&nkey];
+        let mut stock = npc.for_sale(&self.world.items);
+        let Some(pos) = stock.iter().position(|k| self.world.items[k].answers_to(&token)) else {
+            println!("{} no vende eso.", capitalize(&npc.name));
+            return false;
+        };
+        let price = npc.sells[self.world.items[&stock[pos]].proto()];
+        let name = self.world.items[&stock[pos]].the_name();
+        if self.player.gold < price {
+            println!("No te alcanza: {name} cuesta {price} monedas y tienes {}.", self.player.gold);
+            return false;
+        }
+        if self.inventory_full() {
+            self.report_full();
+            return false;
+        }
+        let key = stock.remove(pos);
+        self.world.npcs.get_mut(&nkey).unwrap().stock = Some(stock);
+        // una plantilla se vende como un ejemplar nuevo, con su propio estado
+        let key = self.world.spawn_instance(&key, &nkey).unwrap_or(key);
+        self.player.gold -= price;
+        self.observer.on_item_bought(&key, price);
+        self.player.inventory.push(key);
+        println!("Compras {name} por {price} monedas.");
+        true
+    }
+
+    /// `sell <objeto>`: lo vende al comerciante de la sala, que lo añade a su género.
+    fn cmd_sell(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: sell <objeto>");
+            return false;
+        };
+        let Some(nkey) = self.shopkeeper_here() else {
+            println!("Aquí nadie compra nada.");
+            return false;
+        };
+        let Some(key) = self.find_item_inventory(token) else {
+            self.report_not_carried(token);
+            return false;
+        };
+        let npc = &self.world.npcs[&nkey];
+        let Some(&price) = npc.buys.get(self.world.items[&key].proto()) else {
+            println!("{} no compra eso.", capitalize(&npc.name));
+            return false;
+        };
+        let mut stock = npc.for_sale(&self.world.items);
+        stock.push(key.clone());
+        self.world.npcs.get_mut(&nkey).unwrap().stock = Some(stock);
+        self.player.inventory.retain(|k| k != &key);
+        self.player.gold += price;
+        self.observer.on_item_sold(&key, price);
+        println!("Vendes {} por {price} monedas.", self.world.items[&key].the_name());
+        true
+    }
+
+    fn cmd_use(&mut self, tok: Option<&str>) -> bool {
+        let Some(token) = tok else {
+            println!("Uso: use <objeto>");
+            return false;
+        };
+        // objetos del inventario o, si no, fijos de la sala (palancas, mecanismos)
+        let fixed_here = self
+            .find_item_here(token)
+            .filter(|k| !self.world.items[k].portable);
+        let Some(key) = self.find_item_inventory(token).or(fixed_here) else {
+            let here = self.items_here();
+            let fixed = here.iter().filter(|k| self.world.items.get(*k).is_some_and(|it| !it.portable));
+            match self.suggest_item(token, self.player.inventory.iter().chain(fixed)) {
+                Some(name) => println!("¿Quisiste decir '{name}'?"),
+                None => println!("No llevas eso."),
+            }
+            return false;
+        };
+        if let Some(line) = self.identify(&key) {
+            println!("{line}");
+        }
+        let effects = self.world.items[&key].effects.clone();
+        let handler = EFFECTS.iter().find_map(|(name, f)| effects.get(*name).map(|v| (f, v)));
+        let Some((f, value)) = handler else {
+            println!("No pasa nada.");
+            return false;
+        };
+        for line in f(self, &key, value) {
+            println!("{line}");
+        }
+        // con una luz recién encendida (o alzada) se vuelve a mirar la sala
+        if self.world.items[&key].gives_light() {
+            self.cmd_look();
+        }
+        true
+    }
+
+    /// Marca `key` como identificado; si era un misterio, devuelve el aviso.
+    fn identify(&mut self, key: &str) -> Option<String> {
+        let it = self.world.items.get_mut(key).filter(|it| it.is_unidentified())?;
+        it.identified = true;
+        Some(format!("Ahora sabes qué es {}.", it.the_name()))
+    }
+
+    /// Efecto `identify` (pergaminos y similares): identifica todo lo que llevas.
+    fn effect_identify(&mut self, key: &str, _value: &str) -> Vec<String> {
+        let pending: Vec<String> = self
+            .player
+            .inventory
+            .iter()
+            .filter(|k| self.world.items.get(*k).is_some_and(Item::is_unidentified))
+            .cloned()
+            .collect();
+        if pending.is_empty() {
+            return vec![format!("Usas {}, pero no hay nada que identificar.", self.world.items[key].the_name())];
+        }
+        pending.iter().filter_map(|k| self.identify(k)).collect()
+    }
+
+    /// Efecto `lights`: enciende (o alza) la luz; quien lo llama vuelve a mirar la sala.
+    fn effect_lights(&mut self, key: &str, _value: &str) -> Vec<String> {
+        let item = &self.world.items[key];
+        if item.fuel == Some(0) {
+            return vec![format!("{} está consumida; ya no da luz.", capitalize(&item.the_name()))];
+        }
+        if item.state != "unlit" {
+            return vec![format!("Alzas {}. La luz revela tu entorno.", item.the_name())];
+        }
+        let name = item.the_name();
+        if let Some(it) = self.world.items.get_mut(key) {
+            it.state = "lit".into();
+        }
+        vec![format!("Enciendes {name}. La luz revela tu entorno.")]
+    }
+
+    /// Luz (efecto `lights`) llevada o fija en la sala, para `light` / `extinguish`.
+    fn find_light(&self, tok: Option<&str>, verb: &str) -> Option<String> {
+        let Some(token) = tok else {
+            println!("Uso: {verb} <objeto>");
+            return None;
+        };
+        let Some(key) = self.find_item_inventory(token).or_else(|| self.find_item_here(token)) else {
+            self.report_not_carried(token);
+            return None;
+        };
+        if !self.world.items[&key].effects.contains_key("lights") {
+            println!("Eso no se puede {}.", if verb == "light" { "encender" } else { "apagar" });
+            return None;
+        }
+        Some(key)
+    }
+
+    /// `light <objeto>`: enciende una luz apagada (como `use`, que sigue valiendo).
+    fn cmd_light(&mut self, tok: Option<&str>) -> bool {
+        let Some(key) = self.find_light(tok, "light") else {
+            return false;
+        };
+        if self.world.items[&key].gives_light() {
+            println!("{} ya da luz.", capitalize(&self.world.items[&key].the_name()));
+            return false;
+        }
+        if let Some(it) = self.world.items.get_mut(&key).filter(|it| it.fuel != Some(0)) {
+            it.state = "unlit".into();
+        }
+        for line in self.effect_lights(&key, "") {
+            println!("{line}");
+        }
+        let lit = self.world.items[&key].gives_light();
+        if lit {
+            self.cmd_look();
+        }
+        lit
+    }
+
+    /// `extinguish <objeto>`: la apaga para ahorrar combustible; solo arde encendida.
+    fn cmd_extinguish(&mut self, tok: Option<&str>) -> bool {
+        let Some(key) = self.find_light(tok, "extinguish") else {
+            return false;
+        };
+        let was_lit = self.world.items[&key].gives_light();
+        let it = self.world.items.get_mut(&key).unwrap();
+        if !was_lit {
+            println!("{} no da luz.", capitalize(&it.the_name()));
+            return false;
+        }
+        it.state = "unlit".into();
+        println!("Apagas {}.", it.the_name());
+        if self.in_darkness() {
+            println!("Todo queda a oscuras.");
+        }
+        true
+    }
+
+    /// Efecto `unlocks` ("sala:dir"): como llave, en la propia sala; con `switch`, a distancia.
+    fn effect_unlocks(&mut self, key: &str, tag: &str) -> Vec<String> {
+        let Some((rkey, dir)) = tag.split_once(':').filter(|(_, d)| !d.contains(':')) else {
+            return vec!["La llave no está bien configurada.".into()];
+        };
+        if self.world.items[key].effects.contains_key("switch") {
+            return vec![self.flip_switch(key, rkey, dir)];
+        }
+        if rkey != self.current_room().key {
+            return vec!["No parece servir aquí.".into()];
+        }
+        let flag = format!("locked_{dir}");
+        if !self.current_room().flags.get(&flag).copied().unwrap_or(false) {
+            return vec!["Aquí no hay nada que desbloquear.".into()];
+        }
+        if let Some(r) = self.world.rooms.get_mut(rkey) {
+            r.flags.insert(flag, false);
+        }
+        vec![format!(
+            "Usas {} y desbloqueas la salida {}.",
+            self.world.items[key].the_name(),
+            self.world.dir_label(dir)
+        )]
+    }
+
+    /// Efectos `heal` / `feed` con la cantidad como valor; `feed` cambia el mensaje.
+    fn effect_heal(&mut self, key: &str, value: &str) -> Vec<String> {
+        let feed = self.world.items[key].effects.contains_key("feed");
+        match value.parse::<u32>() {
+            Ok(amount) => self.restore_health(key, amount, feed),
+            Err(_) => vec!["No pasa nada.".into()],
+        }
+    }
+
+    /// Efectos `heal` / `feed`: recupera salud sin pasar de `max_health` y gasta el
+    /// objeto si es consumible.
+    fn restore_health(&mut self, key: &str, amount: u32, feed: bool) -> Vec<String> {
+        let item = &self.world.items[key];
+        if self.player.health >= self.player.max_health {
+            return vec!["Ya estás en plena forma.".into()];
+        }
+        let name = item.the_name();
+        let consumable = item.consumable;
+        let before = self.player.health;
+        self.player.health = (before + amount).min(self.player.max_health);
+        if consumable {
+            self.player.inventory.retain(|k| k != key);
+        }
+        vec![
+            if feed { format!("Comes {name}.") } else { format!("Usas {name}.") },
+            format!("Te sientes mejor (+{}).", self.player.health - before),
+        ]
+    }
+
+    /// Desbloquea `locked_<dir>` en `rkey`, que puede ser una sala distinta de la actual.
+    fn flip_switch(&mut self, key: &str, rkey: &str, dir: &str) -> String {
+        let name = self.world.items[key].the_name();
+        let flag = format!("locked_{dir}");
+        if let Err(e) = self.ensure_room(rkey) {
+            return e.to_string();
+        }
+        match self.world.rooms.get_mut(rkey) {
+            Some(r) if r.flags.get(&flag).copied().unwrap_or(false) => {
+                r.flags.insert(flag, false);
+                format!("Accionas {name}. Oyes un mecanismo a lo lejos.")
+            }
+            Some(_) => format!("Accionas {name}, pero no ocurre nada."),
+            None => format!("{} no parece conectar con nada.", capitalize(&name)),
+        }
+    }
+
+    /// Avanza un paso a cada PNJ con ruta de patrulla y avisa si entra o sale de la sala del jugador.
+    fn step_npcs(&mut self) {
+        let here = self.player.location.clone();
+        let names = &self.world.direction_names;
+        for npc in self.world.npcs.values_mut() {
+            if npc.patrol.is_empty() {
+                continue;
+            }
+            npc.patrol_idx = (npc.patrol_idx + 1) % npc.patrol.len();
+            let from = std::mem::replace(&mut npc.location, npc.patrol[npc.patrol_idx].clone());
+            if from == npc.location {
+                continue;
+            }
+            let Some(room) = self.world.rooms.get(&here) else {
+                continue;
+            };
+            if from == here {
+                match exit_towards(room, &npc.location).and_then(|d| dir_phrase(names, d)) {
+                    Some(d) => println!("{} se marcha hacia {d}.", capitalize(&npc.name)),
+                    None => println!("{} se marcha.", capitalize(&npc.name)),
+                }
+            } else if npc.location == here {
+                match exit_towards(room, &from).and_then(|d| dir_phrase(names, d)) {
+                    Some(d) => println!("{} entra desde {d}.", capitalize(&npc.name)),
+                    None => println!("{} llega.", capitalize(&npc.name)),
+                }
+            }
+        }
+    }
+
+    /// Cada objeto con efecto `poison` que lleve el jugador le quita ese daño por turno.
+    fn apply_poison(&mut self) {
+        let damage: u32 = self
+            .player
+            .inventory
+            .iter()
+            .filter_map(|k| self.world.items.get(k))
+            .filter_map(|it| it.effects.get("poison"))
+            .filter_map(|v| v.parse::<u32>().ok())
+            .sum::<u32>()
+            * self.difficulty.damage_percent
+            / 100;
+        if damage == 0 {
+            return;
+        }
+        self.player.health = self.player.health.saturating_sub(damage);
+        if self.player.health == 0 {
+            self.die("Algo que llevas te ha consumido las últimas fuerzas.");
+        } else if self.player.health <= LOW_HEALTH {
+            println!("Te sientes muy débil ({} de salud).", self.player.health);
+        }
+    }
+
+    /// Todo lo que lleva el jugador, también lo que va dentro de sus recipientes.
+    fn carried_keys(&self) -> Vec<String> {
+        let mut keys = self.player.inventory.clone();
+        let mut i = 0;
+        while i < keys.len() {
+            if let Some(it) = self.world.items.get(&keys[i]) {
+                for k in &it.contents {
+                    if !keys.contains(k) {
+                        keys.push(k.clone());
+                    }
+                }
+            }
+            i += 1;
+        }
+        keys
+    }
+
+    /// Cada luz encendida que lleva el jugador, aunque vaya en un recipiente, gasta
+    /// un turno de su propio combustible; avisa cuando a una le queda poco y cuando
+    /// se consume. Mientras quede otra encendida, la sala sigue iluminada.
+    fn burn_fuel(&mut self) {
+        if !self.difficulty.fuel_burns {
+            return;
+        }
+        for key in self.carried_keys() {
+            let Some(it) = self.world.items.get_mut(&key) else {
+                continue;
+            };
+            if !it.gives_light() {
+                continue;
+            }
+            let Some(fuel) = it.fuel else {
+                continue;
+            };
+            it.fuel = Some(fuel - 1);
+            if fuel == 1 {
+                if !it.state.is_empty() {
+                    it.state = "unlit".into();
+                }
+                let (poss, verb) = if it.plural { ("Tus", "consumen") } else { ("Tu", "consume") };
+                println!("{poss} {} se {verb}.", it.name);
+            } else if fuel - 1 <= FUEL_WARNING && !it.fuel_warned {
+                it.fuel_warned = true;
+                println!("{} parpadea; pronto se apagará.", capitalize(&it.the_name()));
+            }
+        }
+    }
+
+    fn die(&mut self, reason: &str) {
+        println!("{reason}");
+        println!("Has muerto.");
+        let room = self.player.location.clone();
+        self.observer.on_death(&room);
+        self.end_game(Outcome::Death);
+    }
+
+    /// Se sigue jugando: ni se ha salido ni ha terminado la partida.
+    fn in_play(&self) -> bool {
+        self.running && !self.ended
+    }
+
+    /// Fin de la partida sin salir del programa: a partir de aquí solo valen los
+    /// `META_VERBS`, y `outcome` decide el código de salida si no se carga o reinicia.
+    fn end_game(&mut self, outcome: Outcome) {
+        self.outcome = outcome;
+        self.ended = true;
+        self.record_score();
+        println!("\nEl juego ha terminado. Puedes usar restart, load, score o quit.");
+    }
+
+    /// `restart`: vuelve al mundo tal como se cargó, con un jugador nuevo. El cuaderno
+    /// se conserva, que es del jugador y no de la partida.
+    fn cmd_restart(&mut self) {
+        let notes = std::mem::take(&mut self.player.notes);
+        self.world = self.pristine.clone();
+        self.player = Player::new(&self.world, &self.difficulty);
+        self.player.notes = notes;
+        self.turns = 0;
+        self.last_save_turn = 0;
+        self.assisted = false;
+        self.failures.clear();
+        self.ended = false;
+        self.outcome = Outcome::Quit;
+        println!("Empiezas de nuevo.");
+        self.cmd_look();
+    }
+
+    /// `verbs`: cada verbo con los sinónimos que lo invocan.
+    fn cmd_verbs(&self) {
+        let lines: Vec<String> = VERBS
+            .iter()
+            .filter(|v| **v != "solution" || self.allow_solution)
+            .map(|verb| {
+                let aliases: Vec<&str> =
+                    VERB_ALIASES.iter().filter(|(_, v)| v == verb).map(|(alias, _)| *alias).collect();
+                if aliases.is_empty() {
+                    format!("  {verb}")
+                } else {
+                    format!("  {verb}: {}", aliases.join(", "))
+                }
+            })
+            .collect();
+        println!("Verbos (y sinónimos):");
+        self.print_paged(&lines);
+        println!("Una dirección a secas (north, n...) equivale a go.");
+    }
+
+    /// `seed`: la semilla del azar de esta partida, para poder repetirla.
+    fn cmd_seed(&self) {
+        match self.seed {
+            Some(seed) => println!("Semilla: {seed}. Repite esta partida con --seed {seed}."),
+            None => println!("Esta partida no usa azar (se activa con --shuffle)."),
+        }
+    }
+
+    /// `score`: resumen de la partida, también después de terminarla.
+    fn cmd_score(&self) {
+        let result = if self.ended { self.outcome.name() } else { "en curso" };
+        println!("Partida: {result}{}", if self.assisted { " (asistida)" } else { "" });
+        println!("Turnos: {}", self.turns);
+        println!("Salas visitadas: {} de {}", self.player.visited.len(), self.world.rooms.len());
+        println!("Salud: {}/{}", self.player.health, self.player.max_health);
+        println!("Puntos: {}", self.points());
+    }
+
+    /// Puntuación: `POINTS_PER_ROOM` por sala visitada, el oro y `POINTS_FOR_WIN` si se ha ganado.
+    fn points(&self) -> u32 {
+        let win = if self.ended && self.outcome == Outcome::Win { POINTS_FOR_WIN } else { 0 };
+        self.player.visited.len() as u32 * POINTS_PER_ROOM + self.player.gold + win
+    }
+
+    fn scores_path(&self) -> PathBuf {
+        Path::new(self.save_dir.as_deref().unwrap_or(SAVES_DIR)).join(SCORES_FILE)
+    }
+
+    /// Apunta la partida recién terminada en la tabla de puntuaciones. Si el archivo
+    /// está dañado, no se toca (solo se avisa) para no perder lo que hubiera.
+    fn record_score(&self) {
+        if self.demo {
+            return;
+        }
+        let path = self.scores_path();
+        let mut scores = match read_scores(&path) {
+            Ok(scores) => scores,
+            Err(e) => {
+                println!("{e}; esta partida no se apunta.");
+                return;
+            }
+        };
+        scores.push(ScoreEntry {
+            player: self.profile.clone(),
+            score: self.points(),
+            turns: self.turns,
+            outcome: self.outcome.name().to_string(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            assisted: self.assisted,
+        });
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, serde_json::to_string_pretty(&scores).unwrap_or_default()));
+        if let Err(e) = written {
+            println!("No se pudo guardar la puntuación en {}: {e}", path.display());
+        }
+    }
+
+    /// `scores`: las `TOP_SCORES` mejores partidas no asistidas, por puntos y luego por
+    /// menos turnos.
+    fn cmd_scores(&self) {
+        let mut scores = match read_scores(&self.scores_path()) {
+            Ok(scores) => scores,
+            Err(e) => {
+                println!("{e}.");
+                return;
+            }
+        };
+        let assisted = scores.iter().filter(|s| s.assisted).count();
+        scores.retain(|s| !s.assisted);
+        if scores.is_empty() {
+            println!("Todavía no hay puntuaciones.");
+        } else {
+            scores.sort_by(|a, b| b.score.cmp(&a.score).then(a.turns.cmp(&b.turns)));
+            println!("Mejores partidas:");
+            for (i, s) in scores.iter().take(TOP_SCORES).enumerate() {
+                println!("  {:>2}. {:<12} {:>5} puntos {:>5} turnos  {}", i + 1, s.player, s.score, s.turns, s.outcome);
+            }
+        }
+        if assisted > 0 {
+            println!("({assisted} partidas asistidas no entran en la tabla.)");
+        }
+    }
+
+    fn end_turn(&mut self) {
+        self.turns += 1;
+        self.step_npcs();
+        self.burn_fuel();
+        self.apply_poison();
+        self.check_win();
+        self.check_turn_limit();
+    }
+
+    fn holds(&self, cond: &Condition) -> bool {
+        cond.carrying.iter().all(|k| self.carries(k))
+            && !cond.not_carrying.iter().any(|k| self.carries(k))
+            && cond.carrying_tag.iter().all(|t| self.carries_tag(t))
+            && cond.room.iter().all(|r| r == &self.player.location)
+            && cond.flags.iter().all(|f| {
+                f.split_once(':').is_some_and(|(room, flag)| {
+                    self.world.rooms.get(room).is_some_and(|r| r.flags.get(flag).copied().unwrap_or(false))
+                })
+            })
+    }
+
+    /// Si se cumple `win`, muestra el primer final que encaje y termina la partida.
+    fn check_win(&mut self) {
+        if !self.in_play() || !self.world.win.as_ref().is_some_and(|w| self.holds(w)) {
+            return;
+        }
+        let text = self
+            .world
+            .endings
+            .iter()
+            .find(|e| self.holds(&e.condition))
+            .map_or(DEFAULT_ENDING, |e| e.text.as_str());
+        println!();
+        self.print_wrapped(text);
+        println!("\nFin.");
+        self.autosave_event("goal");
+        self.end_game(Outcome::Win);
+    }
+
+    /// Con límite de turnos, avisa en los últimos y termina la partida al agotarlos.
+    fn check_turn_limit(&mut self) {
+        let Some(limit) = self.turn_limit else {
+            return;
+        };
+        if !self.in_play() {
+            return;
+        }
+        if self.turns >= limit {
+            println!("Se te acabó el tiempo.");
+            self.end_game(Outcome::TimeUp);
+        } else if limit - self.turns <= TURN_WARNING {
+            match limit - self.turns {
+                1 => println!("Te queda un solo turno."),
+                left => println!("Te quedan {left} turnos."),
+            }
+        }
+    }
+
+    fn cmd_help(&self) {
+        let text =
+"Comandos:
+  look                 - mirar la sala
+  look under <obj>     - mirar debajo de algo (look behind: detrás)
+  exits                - ver solo las salidas
+  feel                 - palpar a tu alrededor (útil a oscuras)
+  go <dir> [n]         - moverte (north, south, east, west, up, down), n veces
+  <dir>                - moverte sin escribir go (north, n, s, e, w, u, d)
+  climb / enter / exit - subir, entrar (in) o salir (out, o si no down)
+  travel <sala>        - ir a una sala ya visitada por el camino conocido
+  take <objeto>        - tomar objeto
+  take <obj> from <c>  - sacar un objeto de un recipiente
+  put <obj> in <c>     - meter un objeto en un recipiente (también drop ... in)
+  open / close <rec>   - abrir / cerrar un recipiente
+  lock / unlock <dir>  - cerrar / abrir con llave una salida
+  drop <objeto|all>    - soltar objeto (o todo)
+  use <objeto>         - usar objeto (linterna, llave, etc.)
+  apply <h> to <obj>   - aplicar una herramienta a un objeto (cuchillo a cuerda)
+  light / extinguish   - encender / apagar una luz (solo gasta encendida)
+  examine <algo>       - examinar un objeto o a alguien
+  examine me           - tu estado: salud, arma y luces
+  examine all          - descripción de todo lo que llevas (o examine inventory)
+  read <objeto>        - leer (notas, carteles, libros)
+  inv                  - inventario
+  wield <arma>         - empuñar un arma
+  attack <pnj>         - atacar con el arma empuñada (o con los puños)
+  buy / sell <objeto>  - comprar / vender a un comerciante
+  save / load [nombre] - guardar / cargar partida del perfil (o una ruta; - = salida/entrada estándar)
+  saves                - partidas guardadas del perfil
+  saveinfo [nombre]    - qué hay en una partida guardada, sin cargarla
+  profile [nombre]     - perfil activo, o cambiar a otro (se crea si no existe)
+  status [on|off]      - mostrar u ocultar la línea de estado
+  names                - lo que puedes nombrar aquí
+  note add <texto>     - apuntar en tu cuaderno (note list / note clear)
+  score                - resumen de la partida
+  restart              - empezar de nuevo
+  checkpoint <nombre>  - punto de control en memoria (rewind <nombre> vuelve a él)
+  checkpoints          - puntos de control de la sesión
+  scores               - mejores partidas terminadas
+  transcript on <ruta> - copiar la sesión a un archivo (transcript off para parar)
+  map                  - mapa de las salas que conoces
+  about                - versión del motor y datos del mundo
+  verbs                - todos los verbos con sus sinónimos
+  seed                 - semilla del azar, para repetir la partida con --seed
+  help                 - ayuda
+  quit                 - salir";
+        self.print_paged(&text.lines().collect::<Vec<_>>());
+    }
+
+    /// `set <campo> <valor>` (solo con --debug): cambia `health`, `gold`, `location` o
+    /// `name` del jugador, comprobando antes que el valor tenga sentido.
+    #[cfg(feature = "debug-cmds")]
+    fn cmd_set(&mut self, rest: &str) {
+        const USAGE: &str = "Uso: set health|gold|location|name <valor>";
+        let Some((field, value)) = rest.trim().split_once(char::is_whitespace) else {
+            println!("{USAGE}");
+            return;
+        };
+        let value = value.trim();
+        match field.to_lowercase().as_str() {
+            "health" => match value.parse::<u32>() {
+                Ok(n) if (1..=self.player.max_health).contains(&n) => {
+                    self.player.health = n;
+                    println!("Salud: {n}/{}.", self.player.max_health);
+                }
+                Ok(_) => println!("La salud tiene que estar entre 1 y {}.", self.player.max_health),
+                Err(_) => println!("Número no válido: {value}"),
+            },
+            "gold" => match value.parse::<u32>() {
+                Ok(n) => {
+                    self.player.gold = n;
+                    println!("Oro: {n} monedas.");
+                }
+                Err(_) => println!("Número no válido: {value}"),
+            },
+            "location" => {
+                match self.ensure_room(value) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        println!("No existe la sala {value}.");
+                        return;
+                    }
+                    Err(e) => {
+                        println!("{e}");
+                        return;
+                    }
+                }
+                self.player.location = value.to_string();
+                if !self.player.visited.iter().any(|k| k == value) {
+                    self.player.visited.push(value.to_string());
+                }
+                self.cmd_look();
+            }
+            "name" => {
+                self.player.name = value.to_string();
+                println!("Ahora te llamas {value}.");
+            }
+            other => println!("Campo desconocido: {other}. {USAGE}"),
+        }
+    }
+
+    /// `setdesc room <texto>` / `setdesc item <clave> <texto>` (solo con --debug).
+    #[cfg(feature = "debug-cmds")]
+    fn cmd_setdesc(&mut self, rest: &str) {
+        const USAGE: &str = "Uso: setdesc room <texto> | setdesc item <clave> <texto>";
+        let Some((target, text)) = rest.split_once(char::is_whitespace) else {
+            println!("{USAGE}");
+            return;
+        };
+        match target {
+            "room" => {
+                self.current_room_mut().desc = text.trim().to_string();
+                println!("Descripción de la sala actualizada.");
+            }
+            "item" => {
+                let Some((key, text)) = text.trim().split_once(char::is_whitespace) else {
+                    println!("{USAGE}");
+                    return;
+                };
+                let Some(item) = self.world.items.get_mut(key) else {
+                    println!("No existe el objeto {key}.");
+                    return;
+                };
+                item.desc = text.trim().to_string();
+                println!("Descripción de {key} actualizada.");
+            }
+            _ => println!("{USAGE}"),
+        }
+    }
+
+    /// `resetroom` (solo con --debug): devuelve objetos y banderas de la sala actual
+    /// a su estado original. Los objetos que ahora están en otro sitio (inventario,
+    /// otra sala, un recipiente) no se restauran, para no duplicarlos.
+    #[cfg(feature = "debug-cmds")]
+    fn cmd_resetroom(&mut self) {
+        let key = self.player.location.clone();
+        let Some(orig) = self.pristine.rooms.get(&key).cloned() else {
+            println!("La sala no existe en el mundo original.");
+            return;
+        };
+        let elsewhere = |k: &String| {
+            self.player.inventory.contains(k)
+                || self.world.rooms.values().any(|r| r.key != key && r.items.contains(k))
+                || self.world.items.values().any(|it| it.contents.contains(k))
+        };
+        let items: Vec<String> = orig.items.into_iter().filter(|k| !elsewhere(k)).collect();
+        let room = self.current_room_mut();
+        room.items = items;
+        room.flags = orig.flags;
+        println!("Sala reiniciada.");
+    }
+
+    /// `tagged <etiqueta>` (solo con --debug): los objetos con esa etiqueta y dónde están.
+    #[cfg(feature = "debug-cmds")]
+    fn cmd_tagged(&self, tag: Option<&str>) {
+        let Some(tag) = tag else {
+            println!("Uso: tagged <etiqueta>");
+            return;
+        };
+        let tagged: Vec<&Item> = self.world.items.values().filter(|it| it.tags.iter().any(|t| t == tag)).collect();
+        if tagged.is_empty() {
+            println!("Ningún objeto lleva la etiqueta '{tag}'.");
+            return;
+        }
+        for it in tagged {
+            let place = if self.player.inventory.contains(&it.key) {
+                "inventario".to_string()
+            } else if let Some(r) = self.world.rooms.values().find(|r| r.items.contains(&it.key)) {
+                format!("sala {}", r.key)
+            } else if let Some(c) = self.world.items.values().find(|c| c.contents.contains(&it.key)) {
+                format!("dentro de {}", c.key)
+            } else {
+                "en ninguna parte".to_string()
+            };
+            println!("  {} ({}): {place}", it.name, it.key);
+        }
+    }
+
+    /// `clearflags` (solo con --debug): devuelve las banderas de la sala actual a las
+    /// del mundo original, sin tocar los objetos (para eso está `resetroom`).
+    #[cfg(feature = "debug-cmds")]
+    fn cmd_clearflags(&mut self) {
+        let Some(flags) = self.pristine.rooms.get(&self.player.location).map(|r| r.flags.clone()) else {
+            println!("La sala no existe en el mundo original.");
+            return;
+        };
+        self.current_room_mut().flags = flags;
+        println!("Banderas de la sala restauradas.");
+    }
+
+    /// `path <sala> [--honor-locks]` (solo con --debug).
+    #[cfg(feature = "debug-cmds")]
+    fn cmd_path(&self, rest: &str) {
+        let mut target = None;
+        let mut honor_locks = false;
+        for arg in rest.split_whitespace() {
+            match arg {
+                "--honor-locks" => honor_locks = true,
+                _ => target = Some(arg),
+            }
+        }
+        let Some(target) = target else {
+            println!("Uso: path <sala> [--honor-locks]");
+            return;
+        };
+        if !self.world.rooms.contains_key(target) {
+            println!("No existe la sala {target}.");
+            return;
+        }
+        match self.world.find_path(&self.player.location, target, honor_locks, None) {
+            Some(dirs) if dirs.is_empty() => println!("Ya estás ahí."),
+            Some(dirs) => println!("Ruta: {}", dirs.join(", ")),
+            None => println!("Sin ruta."),
+        }
+    }
+
+    /// Vuelca el `World` en memoria (con las ediciones hechas) a un JSON. Lo que solo
+    /// cambia jugando (estado, combustible, veces examinado, escondites mirados) se
+    /// escribe como en el mundo original, para que el fichero sirva de mundo nuevo.
+    #[cfg(feature = "debug-cmds")]
+    fn write_world(&self, path: &str) -> Result<()> {
+        let mut world = self.world.clone();
+        for (key, item) in world.items.iter_mut() {
+            let orig = self.pristine.items.get(key).or_else(|| self.pristine.items.get(item.proto()));
+            if let Some(orig) = orig {
+                item.state = orig.state.clone();
+                item.fuel = orig.fuel;
+                item.fuel_warned = orig.fuel_warned;
+                item.searched = orig.searched.clone();
+                item.examined = orig.examined;
+            }
+        }
+        let data = serde_json::to_string_pretty(&world)?;
+        fs::write(path, data)?;
+        println!("Mundo escrito en {path}");
+        Ok(())
+    }
+
+    /// Estado de la partida en el formato de las partidas guardadas.
+    fn save_data(&self) -> SaveData {
+        SaveData {
+            player: self.player.clone(),
+            turns: self.turns,
+            assisted: self.assisted,
+            turn_limit: self.turn_limit,
+            last_save_turn: Some(self.last_save_turn),
+            rooms: self
+                .world
+                .rooms
+                .iter()
+                .map(|(k, r)| {
+                    (
+                        k.clone(),
+                        RoomState {
+                            items: r.items.clone(),
+                            flags: r.flags.clone(),
+                        },
+                    )
+                })
+                .collect(),
+            items: self
+                .world
+                .items
+                .iter()
+                .filter(|(_, it)| {
+                    it.container
+                        || it.fuel.is_some()
+                        || !it.state.is_empty()
+                        || it.unidentified_desc.is_some()
+                        || !it.under.is_empty()
+                        || !it.behind.is_empty()
+                        || it.examine_limit.is_some()
+                        || it.prototype.is_some()
+                })
+                .map(|(k, it)| {
+                    (
+                        k.clone(),
+                        ItemState {
+                            prototype: it.prototype.clone(),
+                            closed: it.closed,
+                            contents: it.contents.clone(),
+                            fuel: it.fuel,
+                            fuel_warned: it.fuel_warned,
+                            state: it.state.clone(),
+                            identified: it.identified,
+                            searched: it.searched.clone(),
+                            examined: it.examined,
+                        },
+                    )
+                })
+                .collect(),
+            npcs: self
+                .world
+                .npcs
+                .iter()
+                .map(|(k, n)| {
+                    (
+                        k.clone(),
+                        NpcState {
+                            location: n.location.clone(),
+                            patrol_idx: n.patrol_idx,
+                            stock: n.stock.clone(),
+                            health: n.health,
+                        },
+                    )
+                })
+                .collect(),
+            defeated: self.pristine.npcs.keys().filter(|k| !self.world.npcs.contains_key(*k)).cloned().collect(),
+            version: SAVE_VERSION,
+            world_id: self.world.world_id.clone(),
+            ended: self.ended.then_some(self.outcome),
+        }
+    }
+
+    /// Guarda la partida en `path` ("-" = salida estándar).
+    pub fn save(&mut self, path: &str) -> Result<(), GameError> {
+        let snapshot = SaveData { last_save_turn: Some(self.turns), ..self.save_data() };
+        let data = serde_json::to_string_pretty(&snapshot).map_err(GameError::Serialize)?;
+        if path == "-" {
+            // a la salida estándar, sin más texto, para poder encadenarlo
+            println!("{data}");
+        } else {
+            if let Some(dir) = Path::new(path).parent().filter(|d| !d.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, data)?;
+            println!("Juego guardado en {path}");
+        }
+        self.last_save_turn = self.turns;
+        Ok(())
+    }
+
+    /// Con `path` = "-" lee un único documento JSON de la entrada estándar; lo que siga
+    /// se sigue leyendo como órdenes.
+    pub fn load(&mut self, path: &str) -> Result<(), GameError> {
+        self.restore(read_save(path)?)?;
+        if path == "-" {
+            println!("Juego cargado desde la entrada estándar");
+        } else {
+            println!("Juego cargado desde {path}");
+        }
+        self.cmd_look();
+        if self.ended {
+            println!("\nEsta partida ya había terminado. Puedes usar restart, load, score o quit.");
+        }
+        Ok(())
+    }
+
+    /// Vuelve al estado de `snapshot` (de una partida guardada o un punto de control).
+    fn restore(&mut self, snapshot: SaveData) -> Result<(), GameError> {
+        if let Some(msg) = save_mismatch(&self.world, &snapshot) {
+            return Err(GameError::InvalidSave(msg));
+        }
+        if !self.ensure_room(&snapshot.player.location)? {
+            return Err(GameError::InvalidSave(format!(
+                "La partida está en la sala '{}', que no existe en este mundo",
+                snapshot.player.location
+            )));
+        }
+        self.player = snapshot.player;
+        self.turns = snapshot.turns;
+        self.last_save_turn = snapshot.last_save_turn.unwrap_or(snapshot.turns);
+        if !self.player.visited.contains(&self.player.location) {
+            // partidas anteriores a `travel`
+            self.player.visited.push(self.player.location.clone());
+        }
+        // las salas que este mundo ya no tiene no pueden ser destino de `travel`
+        let mut visited = std::mem::take(&mut self.player.visited);
+        for k in &visited {
+            self.ensure_room(k)?;
+        }
+        visited.retain(|k| self.world.rooms.contains_key(k));
+        self.player.visited = visited;
+        self.assisted = snapshot.assisted;
+        // una partida guardada ya terminada se carga terminada: solo quedan los META_VERBS
+        self.ended = snapshot.ended.is_some();
+        self.outcome = snapshot.ended.unwrap_or(Outcome::Quit);
+        if snapshot.turn_limit.is_some() {
+            self.turn_limit = snapshot.turn_limit;
+        }
+        for (k, st) in snapshot.rooms {
+            self.ensure_room(&k)?;
+            if let Some(r) = self.world.rooms.get_mut(&k) {
+                r.items = st.items;
+                r.flags = st.flags;
+            }
+        }
+        for (k, st) in snapshot.items {
+            if let Some(proto) = st.prototype.as_deref().filter(|_| !self.world.items.contains_key(&k)) {
+                self.world.add_instance(proto, &k);
+            }
+            if let Some(it) = self.world.items.get_mut(&k) {
+                it.closed = st.closed;
+                it.contents = st.contents;
+                it.fuel = st.fuel;
+                it.fuel_warned = st.fuel_warned;
+                it.state = st.state;
+                it.identified = st.identified;
+                it.searched = st.searched;
+                it.examined = st.examined;
+            }
+        }
+        // los derrotados en esta sesión vuelven si en la partida cargada seguían vivos
+        for (k, npc) in &self.pristine.npcs {
+            if !snapshot.defeated.contains(k) && !self.world.npcs.contains_key(k) {
+                self.world.npcs.insert(k.clone(), npc.clone());
+            }
+        }
+        self.world.npcs.retain(|k, _| !snapshot.defeated.contains(k));
+        for (k, st) in snapshot.npcs {
+            if let Some(n) = self.world.npcs.get_mut(&k) {
+                n.location = st.location;
+                n.patrol_idx = st.patrol_idx;
+                if st.stock.is_some() {
+                    n.stock = st.stock;
+                }
+                if st.health.is_some() {
+                    n.health = st.health;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ha ocurrido `event`; si está en --autosave-on, se guarda al acabar el comando
+    /// (una sola vez aunque ocurran varios).
+    fn autosave_event(&mut self, event: &str) {
+        if !self.demo && self.autosave_on.iter().any(|e| e == event) {
+            self.autosave_pending = true;
+        }
+    }
+
+    /// Guarda en la ranura `AUTOSAVE_SLOT` del perfil.
+    fn autosave(&mut self) {
+        let path = slot_path(self.save_dir.as_deref(), &self.profile, Some(AUTOSAVE_SLOT));
+        if let Err(e) = self.save(&path) {
+            println!("No se pudo autoguardar: {e}");
+        }
+    }
+
+    /// `checkpoint <nombre>`: guarda el estado actual en memoria con ese nombre (uno
+    /// existente se sobrescribe), hasta `MAX_CHECKPOINTS`.
+    fn cmd_checkpoint(&mut self, name: Option<&str>) {
+        let Some(name) = name else {
+            println!("Uso: checkpoint <nombre>");
+            return;
+        };
+        if !self.checkpoints.contains_key(name) && self.checkpoints.len() >= MAX_CHECKPOINTS {
+            println!("Ya tienes {MAX_CHECKPOINTS} puntos de control; sobrescribe uno de ellos.");
+            return;
+        }
+        self.checkpoints.insert(name.to_string(), self.save_data());
+        println!("Punto de control '{name}' creado (turno {}).", self.turns);
+    }
+
+    /// `rewind <nombre>`: vuelve al punto de control, que sigue disponible.
+    fn cmd_rewind(&mut self, name: Option<&str>) {
+        let Some(name) = name else {
+            println!("Uso: rewind <nombre>");
+            return;
+        };
+        let Some(snapshot) = self.checkpoints.get(name).cloned() else {
+            println!("No hay ningún punto de control '{name}'.");
+            return;
+        };
+        match self.restore(snapshot) {
+            Ok(()) => {
+                println!("Vuelves al punto de control '{name}'.");
+                self.cmd_look();
+            }
+            Err(e) => println!("{e}"),
+        }
+    }
+
+    /// `checkpoints`: los puntos de control de la sesión, por orden de creación.
+    fn cmd_checkpoints(&self) {
+        if self.checkpoints.is_empty() {
+            println!("No hay puntos de control. Crea uno con checkpoint <nombre>.");
+            return;
+        }
+        println!("Puntos de control:");
+        for (name, cp) in &self.checkpoints {
+            let room = self.world.rooms.get(&cp.player.location).map_or(cp.player.location.as_str(), |r| r.name.as_str());
+            println!("  {name} (turno {}, {room})", cp.turns);
+        }
+    }
+
+    fn status_line(&self) -> String {
+        format!(
+            "[{} | Salud {}/{} | Turno {}{} | Luz: {}{}]",
+            self.current_room().name,
+            self.player.health,
+            self.player.max_health,
+            self.turns,
+            self.turn_limit.map(|l| format!("/{l}")).unwrap_or_default(),
+            if self.has_light() { "sí" } else { "no" },
+            match self.unsaved_turns() {
+                0 => String::new(),
+                n => format!(" | Sin guardar: {n}"),
+            }
+        )
+    }
+
+    /// Turnos jugados desde el último `save` (o desde el inicio).
+    fn unsaved_turns(&self) -> u64 {
+        self.turns.saturating_sub(self.last_save_turn)
+    }
+
+    fn cmd_status(&mut self, arg: Option<&str>) {
+        self.show_status = match arg {
+            Some("on") => true,
+            Some("off") => false,
+            _ => !self.show_status,
+        };
+        println!("Línea de estado {}.", if self.show_status { "activada" } else { "desactivada" });
+    }
+
+    /// `note add <texto>` / `note list` / `note clear`: cuaderno del jugador.
+    fn cmd_note(&mut self, rest: &str) {
+        let (sub, text) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        match sub.to_lowercase().as_str() {
+            "add" | "anadir" | "añadir" if !text.trim().is_empty() => {
+                if self.player.notes.len() >= MAX_NOTES {
+                    println!("El cuaderno está lleno ({MAX_NOTES} notas). Usa 'note clear'.");
+                    return;
+                }
+                self.player.notes.push(text.trim().to_string());
+                println!("Anotado ({}).", self.player.notes.len());
+            }
+            "" | "list" | "lista" => {
+                if self.player.notes.is_empty() {
+                    println!("El cuaderno está vacío.");
+                    return;
+                }
+                let lines: Vec<String> =
+                    self.player.notes.iter().enumerate().map(|(i, n)| format!("{:>3}. {n}", i + 1)).collect();
+                self.print_paged(&lines);
+            }
+            "clear" | "borrar" => {
+                self.player.notes.clear();
+                println!("Cuaderno borrado.");
+            }
+            _ => println!("Uso: note add <texto> | note list | note clear"),
+        }
+    }
+
+    /// `profile`: muestra el perfil activo y los que hay; `profile <nombre>` cambia a
+    /// otro, creando su carpeta si es nuevo.
+    fn cmd_profile(&mut self, name: Option<&str>) {
+        let Some(name) = name else {
+            println!("Perfil activo: {}", self.profile);
+            let root = Path::new(self.save_dir.as_deref().unwrap_or(SAVES_DIR));
+            let mut others: Vec<String> = fs::read_dir(root)
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .filter(|e| e.path().is_dir())
+                        .map(|e| e.file_name().to_string_lossy().into_owned())
+                        .filter(|n| n != &self.profile)
+                        .collect()
+                })
+                .unwrap_or_default();
+            others.sort();
+            if !others.is_empty() {
+                println!("Otros perfiles: {}", others.join(", "));
+            }
+            return;
+        };
+        if !valid_name(name) {
+            println!("Nombre de perfil no válido: usa letras, números, - o _.");
+            return;
+        }
+        let dir = profile_dir(self.save_dir.as_deref(), name);
+        let created = !dir.exists();
+        if let Err(e) = fs::create_dir_all(&dir) {
+            println!("No se pudo crear {}: {e}", dir.display());
+            return;
+        }
+        self.profile = name.to_string();
+        let verb = if created { "Perfil creado" } else { "Perfil activo" };
+        println!("{verb}: {name} ({})", dir.display());
+    }
+
+    /// `transcript on <ruta>` / `transcript off`.
+    fn cmd_transcript(&self, rest: &str) {
+        let (action, path) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        match (action.to_lowercase().as_str(), path.trim()) {
+            ("on", "") => println!("Uso: transcript on <ruta>"),
+            ("on", path) => match start_transcript(path) {
+                Ok(()) => println!("Transcripción en {path}."),
+                Err(e) => println!("{e}"),
+            },
+            ("off", _) if transcript_active() => {
+                // el aviso va antes de cerrar, para que quede en la propia transcripción
+                println!("Transcripción detenida.");
+                stop_transcript();
+            }
+            ("off", _) => println!("No hay ninguna transcripción en marcha."),
+            _ => println!("Uso: transcript on <ruta> | transcript off"),
+        }
+    }
+
+    /// `saves`: partidas guardadas del perfil activo.
+    fn cmd_saves(&self) {
+        let dir = profile_dir(self.save_dir.as_deref(), &self.profile);
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|x| x == "json"))
+                    .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        if names.is_empty() {
+            println!("No hay partidas guardadas en el perfil {}.", self.profile);
+            return;
+        }
+        println!("Partidas del perfil {}:", self.profile);
+        for n in names {
+            println!("  {n}");
+        }
+    }
+
+    /// `saveinfo [nombre]`: qué hay en una partida del perfil (o una ruta), sin cargarla.
+    fn cmd_saveinfo(&self, target: Option<&str>) {
+        let path = slot_path(self.save_dir.as_deref(), &self.profile, target);
+        match read_save(&path) {
+            Ok(save) => {
+                println!("Partida {path}:");
+                for line in save_summary(&save) {
+                    println!("  {line}");
+                }
+            }
+            Err(e) => println!("No se puede leer la partida {path}: {e}"),
+        }
+    }
+
+    /// `names`: lo que se puede nombrar ahora mismo (nombre y clave). A oscuras, solo
+    /// lo que se lleva encima.
+    fn cmd_names(&self) {
+        let dark = self.in_darkness();
+        let entry = |k: &String| self.world.items.get(k).map(|it| format!("{} ({})", it.name, it.key));
+        let groups: [(&str, Vec<String>); 3] = [
+            (
+                "En la sala",
+                if dark { vec![] } else { self.items_here().iter().filter_map(entry).collect() },
+            ),
+            ("Contigo", self.player.inventory.iter().filter_map(entry).collect()),
+            (
+                "Personajes",
+                if dark {
+                    vec![]
+                } else {
+                    self.world
+                        .npcs
+                        .values()
+                        .filter(|n| n.location == self.player.location)
+                        .map(|n| format!("{} ({})", n.name, n.key))
+                        .collect()
+                },
+            ),
+        ];
+        if groups.iter().all(|(_, names)| names.is_empty()) {
+            println!("No distingues nada que puedas nombrar.");
+            return;
+        }
+        for (title, names) in groups.iter().filter(|(_, names)| !names.is_empty()) {
+            println!("{title}: {}", names.join(", "));
+        }
+    }
+
+    /// `map`: las salas visitadas, con `@` donde estás. `map flags` (con --debug) dibuja
+    /// todas con marcas: `D` oscura, `L` con alguna salida bloqueada, `T` bandera `trap`.
+    fn cmd_map(&self, flags: bool) {
+        let rooms: Vec<String> = if flags {
+            self.world.rooms.keys().cloned().collect()
+        } else {
+            self.world.rooms.keys().filter(|k| self.player.visited.contains(k)).cloned().collect()
+        };
+        let number = |key: &str| rooms.iter().position(|k| k == key).unwrap_or(0) + 1;
+        let label = |key: &str| {
+            let room = &self.world.rooms[key];
+            let mut text = number(key).to_string();
+            if flags {
+                let set = |f: &str| room.flags.get(f).copied().unwrap_or(false);
+                if set("dark") {
+                    text.push('D');
+                }
+                if room.flags.iter().any(|(f, &on)| on && f.starts_with("locked_")) {
+                    text.push('L');
+                }
+                if set("trap") {
+                    text.push('T');
+                }
+            } else if key == self.player.location {
+                text.insert(0, '@');
+            }
+            text
+        };
+        let mut lines = Vec::new();
+        for (i, grid) in map_layout(&self.world, &rooms).iter().enumerate() {
+            if i > 0 {
+                lines.push(String::new());
+            }
+            lines.extend(render_level(&self.world, grid, label));
+        }
+        lines.push(String::new());
+        for (i, key) in rooms.iter().enumerate() {
+            let room = &self.world.rooms[key];
+            let name = if flags { format!("{} ({key})", room.name) } else { room.name.clone() };
+            lines.push(format!("{:>3}  {name}", i + 1));
+        }
+        self.print_paged(&lines);
+    }
+
+    /// `about`: versión del motor y datos del mundo, para adjuntar a un informe de fallo.
+    fn cmd_about(&self) {
+        println!("mud_offline {}", env!("CARGO_PKG_VERSION"));
+        println!("Mundo: {}", self.world.title.as_deref().unwrap_or("(sin título)"));
+        println!("Id del mundo: {}", self.world.world_id.as_deref().unwrap_or("(sin id)"));
+        println!("Idioma: {}", self.lang);
+        println!("Dificultad: {}", self.difficulty.level.name());
+    }
+
+    /// `solution` (con --allow-solution): muestra la solución del autor tras confirmar
+    /// y marca la partida como asistida.
+    fn cmd_solution(&mut self) {
+        if self.world.walkthrough.is_empty() {
+            println!("Este mundo no incluye una solución.");
+            return;
+        }
+        if !self.assisted && !self.difficulty.free_hints {
+            print!("Ver la solución marcará la partida como asistida. ¿Seguro? (s/n) ");
+            io::stdout().flush().ok();
+            let mut buf = String::new();
+            io::stdin().read_line(&mut buf).ok();
+            if !matches!(buf.trim().to_lowercase().as_str(), "s" | "si" | "sí" | "y" | "yes") {
+                println!("Mejor así. ¡Suerte!");
+                return;
+            }
+            self.assisted = true;
+        }
+        println!("Solución:");
+        for (i, step) in self.world.walkthrough.iter().enumerate() {
+            println!("  {}. {step}", i + 1);
+        }
+    }
+
+    /// Vista de solo lectura del estado actual, pensada para pruebas e interfaces.
+    /// A oscuras no incluye los objetos de la sala. Siempre está disponible; lo que
+    /// depende de `debug-cmds` es solo el comando `view` que la muestra.
+    #[cfg_attr(not(feature = "debug-cmds"), allow(dead_code))]
+    fn snapshot(&self) -> GameView {
+        let room = self.current_room();
+        let item_names = |keys: &[String]| -> Vec<String> {
+            keys.iter()
+                .filter_map(|k| self.world.items.get(k).map(|it| it.name.clone()))
+                .collect()
+        };
+        let mut exits: Vec<String> = room.exits.keys().cloned().collect();
+        exits.sort();
+        GameView {
+            room_key: room.key.clone(),
+            room_name: room.name.clone(),
+            room_desc: room.desc.clone(),
+            items_here: if self.in_darkness() { vec![] } else { item_names(&self.items_here()) },
+            exits,
+            inventory: item_names(&self.player.inventory),
+            health: self.player.health,
+            turns: self.turns,
+        }
+    }
+
+    fn intro(&self) {
+        println!("Bienvenido al mini-MUD (offline). Escribe 'help' para ver comandos.");
+        if self.difficulty.level != Level::Normal {
+            println!("Dificultad: {}.", self.difficulty.level.name());
+        }
+        if let Some(seed) = self.seed {
+            println!("Semilla: {seed} (repite esta partida con --seed {seed}).");
+        }
+        println!();
+        self.cmd_look();
+    }
+
+    /// Apunta el comando si ha fallado y, al repetirse el mismo fallo `NUDGE_AFTER`
+    /// veces entre los últimos `NUDGE_HISTORY`, añade la pista que dejó el comando.
+    fn track_failure(&mut self, line: &str) {
+        let Some(nudge) = self.failed.take() else {
+            return;
+        };
+        self.failures.push(line.to_lowercase());
+        if self.failures.len() > NUDGE_HISTORY {
+            self.failures.remove(0);
+        }
+        let repeats = self.failures.iter().filter(|l| **l == line.to_lowercase()).count();
+        if self.nudges && repeats == NUDGE_AFTER {
+            println!("{nudge}");
+        }
+    }
+
+    fn loop_run(&mut self) -> Outcome {
+        while self.running {
+            // la línea de estado es solo para terminales; no ensucia la salida redirigida
+            if self.show_status && io::stdout().is_terminal() {
+                print!("\n{}", self.status_line());
+            }
+            print!("\n> ");
+            io::stdout().flush().ok();
+            let mut buf = String::new();
+            let truncated = match self.read_command(&mut buf) {
+                Ok((0, _)) | Err(_) => {
+                    println!("\nSaliendo…");
+                    break;
+                }
+                Ok((_, truncated)) => truncated,
+            };
+            transcribe(&buf);
+            if truncated {
+                transcribe("\n");
+                println!("Entrada demasiado larga; truncada.");
+            }
+            self.run_command(&buf);
+        }
+        self.outcome
+    }
+
+    /// Siguiente línea de órdenes: la siguiente de `replies` si las hay (agotadas, es el
+    /// fin de la entrada) y, si no, la de la entrada estándar.
+    fn read_command(&mut self, buf: &mut String) -> io::Result<(usize, bool)> {
+        let Some(replies) = &mut self.replies else {
+            return read_input_line(&mut io::stdin().lock(), buf);
+        };
+        Ok(match replies.pop_front() {
+            Some(line) => {
+                buf.push_str(&line);
+                buf.push('\n');
+                (buf.len(), false)
+            }
+            None => (0, false),
+        })
+    }
+
+    /// `--demo`: juega `commands` una y otra vez, con `delay` antes de cada orden, y
+    /// empieza de nuevo al terminar el guion. En una terminal se para al pulsar Intro;
+    /// si la entrada no es una terminal, el guion se juega una sola vez. Después se
+    /// vuelve a empezar para que juegue la persona.
+    fn run_demo(&mut self, commands: &[String], delay: Duration) {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let interactive = io::stdin().is_terminal();
+        let stop = Arc::new(AtomicBool::new(false));
+        if interactive {
+            println!("Modo demostración: pulsa Intro para jugar.");
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                let mut buf = String::new();
+                io::stdin().read_line(&mut buf).ok();
+                stop.store(true, Ordering::Relaxed);
+            });
+        }
+        self.play_demo(commands, delay, interactive, || stop.load(Ordering::Relaxed));
+    }
+
+    /// Bucle de `run_demo`: juega el guion (una y otra vez si `looping`) hasta que
+    /// `stop` diga que basta, y deja preparada una partida nueva.
+    fn play_demo(&mut self, commands: &[String], delay: Duration, looping: bool, mut stop: impl FnMut() -> bool) {
+        self.demo = true;
+        'demo: loop {
+            for cmd in commands {
+                if stop() {
+                    break 'demo;
+                }
+                print!("\n> ");
+                io::stdout().flush().ok();
+                std::thread::sleep(delay);
+                println!("{cmd}");
+                self.run_command(cmd);
+                // un `quit` en el guion no cierra la demostración
+                self.running = true;
+            }
+            if !looping {
+                break;
+            }
+            println!();
+            self.cmd_restart();
+        }
+        self.demo = false;
+        println!("\nFin de la demostración. Te toca.");
+        self.cmd_restart();
+    }
+
+    /// Ejecuta una línea de órdenes tal como la escribe el jugador.
+    pub fn run_command(&mut self, buf: &str) {
+        let line = normalize_input(buf.trim());
+        if line.is_empty() {
+            return;
+        }
+        let words: Vec<String> = tokenize(&line).into_iter().map(|(w, _)| w).collect();
+        let cmd = words[0].to_lowercase();
+        let verb = canonical_verb(&cmd);
+        let arg1 = words.get(1).map(String::as_str);
+        let arg2 = words.get(2).map(String::as_str);
+        let rest = line.split_once(char::is_whitespace).map(|(_, r)| r.trim()).unwrap_or("");
+        // objeto completo, para nombres de varias palabras ("llave vieja")
+        let target_text = words[1..].join(" ");
+        let target = (!target_text.is_empty()).then_some(target_text.as_str());
+        // las acciones sobre el mundo gastan un turno (y hacen avanzar PNJs, luces,
+        // veneno...) solo si salen bien; un intento fallido no cuesta nada
+        let mut acted = false;
+        if self.ended && !META_VERBS.contains(&verb) {
+            println!("El juego ha terminado.");
+            return;
+        }
+
+        let mut handled = true;
+        match verb {
+            "look" => match arg1 {
+                Some("under" | "debajo") => self.cmd_look_hidden("under", &words[2..].join(" ")),
+                Some("behind" | "detras" | "detrás") => self.cmd_look_hidden("behind", &words[2..].join(" ")),
+                _ => self.cmd_look(),
+            },
+            "exits" => self.cmd_exits(),
+            "feel" => self.cmd_feel(),
+            "go" => acted = self.cmd_go(arg1, arg2),
+            "climb" | "enter" | "exit" => acted = self.cmd_go_verb(verb),
+            "travel" => acted = self.cmd_travel(target),
+            "take" => {
+                acted = match split_on(rest, "from") {
+                    Some((item, cont)) => self.cmd_take_from(&item, &cont),
+                    None => self.cmd_take(target),
+                }
+            }
+            "lock" => acted = self.cmd_lock(arg1, true),
+            "unlock" => acted = self.cmd_lock(arg1, false),
+            "open" => acted = self.cmd_open(target, true),
+            "close" => acted = self.cmd_open(target, false),
+            "drop" => {
+                acted = match split_on(rest, "in").or_else(|| split_on(rest, "en")) {
+                    Some((item, cont)) => self.cmd_put(&item, &cont),
+                    None => self.cmd_drop(target),
+                }
+            }
+            "put" => match split_on(rest, "in").or_else(|| split_on(rest, "en")) {
+                Some((item, cont)) => acted = self.cmd_put(&item, &cont),
+                None => println!("Uso: put <objeto> in <recipiente>"),
+            },
+            "apply" => match split_on(rest, "to").or_else(|| split_on(rest, "a")) {
+                Some((tool, obj)) => acted = self.cmd_apply(&tool, &obj),
+                None => println!("Uso: apply <herramienta> to <objeto>"),
+            },
+            "use" => acted = self.cmd_use(target),
+            "light" => acted = self.cmd_light(target),
+            "extinguish" => acted = self.cmd_extinguish(target),
+            "buy" => acted = self.cmd_buy(target),
+            "sell" => acted = self.cmd_sell(target),
+            "examine" => self.cmd_examine(target),
+            "examinarme" => self.examine_self(),
+            "read" => self.cmd_read(target),
+            "inventory" => self.cmd_inventory(),
+            "wield" => acted = self.cmd_wield(target),
+            "attack" => acted = self.cmd_attack(target),
+            "save" => {
+                let path = slot_path(self.save_dir.as_deref(), &self.profile, target);
+                if let Err(e) = self.save(&path) {
+                    println!("{e}");
+                }
+            }
+            "load" => {
+                let path = slot_path(self.save_dir.as_deref(), &self.profile, target);
+                if let Err(e) = self.load(&path) {
+                    println!("{e}");
+                }
+            }
+            "help" => self.cmd_help(),
+            "status" => self.cmd_status(arg1),
+            "about" => self.cmd_about(),
+            "seed" => self.cmd_seed(),
+            "verbs" => self.cmd_verbs(),
+            "names" => self.cmd_names(),
+            "profile" => self.cmd_profile(arg1),
+            "saves" => self.cmd_saves(),
+            "saveinfo" => self.cmd_saveinfo(target),
+            "note" => self.cmd_note(rest),
+            "transcript" => self.cmd_transcript(rest),
+            "map" => self.cmd_map(cfg!(feature = "debug-cmds") && self.debug && arg1 == Some("flags")),
+            #[cfg(feature = "debug-cmds")]
+            "setdesc" if self.debug => self.cmd_setdesc(rest),
+            #[cfg(feature = "debug-cmds")]
+            "set" if self.debug => self.cmd_set(rest),
+            #[cfg(feature = "debug-cmds")]
+            "path" if self.debug => self.cmd_path(rest),
+            #[cfg(feature = "debug-cmds")]
+            "tagged" if self.debug => self.cmd_tagged(arg1),
+            #[cfg(feature = "debug-cmds")]
+            "resetroom" if self.debug => self.cmd_resetroom(),
+            #[cfg(feature = "debug-cmds")]
+            "clearflags" if self.debug => self.cmd_clearflags(),
+            #[cfg(feature = "debug-cmds")]
+            "view" if self.debug => match serde_json::to_string_pretty(&self.snapshot()) {
+                Ok(json) => println!("{json}"),
+                Err(e) => println!("{e}"),
+            },
+            "solution" if self.allow_solution => self.cmd_solution(),
+            #[cfg(feature = "debug-cmds")]
+            "writeworld" if self.debug => {
+                match arg1 {
+                    Some(path) => { if let Err(e) = self.write_world(path) { println!("{e}"); } }
+                    None => println!("Uso: writeworld <ruta>"),
+                }
+            }
+            "restart" => self.cmd_restart(),
+            "checkpoint" => self.cmd_checkpoint(target),
+            "rewind" => self.cmd_rewind(target),
+            "checkpoints" => self.cmd_checkpoints(),
+            "score" => self.cmd_score(),
+            "scores" => self.cmd_scores(),
+            "quit" if self.ended => self.running = false,
+            "quit" => {
+                match self.unsaved_turns() {
+                    0 => {}
+                    1 => println!("Has hecho 1 movimiento desde el último guardado."),
+                    n => println!("Has hecho {n} movimientos desde el último guardado."),
+                }
+                self.running = false;
+                println!("¡Hasta la próxima!");
+            }
+            // una dirección a secas ("north", "n") es `go`, si no era ya otro verbo
+            _ if words.len() == 1 && self.world.is_direction(&cmd) => acted = self.cmd_go(Some(&cmd), None),
+            _ => {
+                handled = false;
+                let msg = self.world.unknown_command_message.as_deref().unwrap_or(UNKNOWN_COMMAND);
+                println!("{}", msg.replace("{input}", buf.trim()));
+            }
+        }
+        self.track_failure(&line);
+        if self.verbose {
+            let args = &words[1..];
+            eprintln!(
+                "[traza] entrada={:?} normalizada={line:?} verbo={verb}{} args={args:?} manejador={}",
+                buf.trim(),
+                if verb != cmd { format!(" (alias {cmd})") } else { String::new() },
+                if handled { verb } else { "ninguno" },
+            );
+        }
+
+        if acted && self.in_play() {
+            self.end_turn();
+        }
+        // el objetivo cumplido también se guarda, aunque con él termine la partida
+        if std::mem::take(&mut self.autosave_pending) && (self.in_play() || self.outcome == Outcome::Win) {
+            self.autosave();
+        }
+    }
+}
+
+/// Modelo de lectura devuelto por `Game::snapshot` (distinto de `SaveData`, que es
+/// el de persistencia).
+#[derive(Debug, Clone, Serialize)]
+struct GameView {
+    room_key: String,
+    room_name: String,
+    room_desc: String,
+    items_here: Vec<String>,
+    exits: Vec<String>,
+    inventory: Vec<String>,
+    health: u32,
+    turns: u64,
+}
+
+/// Nombre de la partida guardada por `save` / `load` sin ruta.
+const SAVE_PATH: &str = "save.json";
+
+/// Carpeta de perfiles si no se indica `$MUD_SAVE_DIR`; cada perfil tiene la suya.
+const SAVES_DIR: &str = "saves";
+const DEFAULT_PROFILE: &str = "default";
+
+/// Carpeta de partidas de `profile`: `<dir>/<perfil>/`, con `dir` = `$MUD_SAVE_DIR` o `SAVES_DIR`.
+fn profile_dir(dir: Option<&str>, profile: &str) -> PathBuf {
+    Path::new(dir.unwrap_or(SAVES_DIR)).join(profile)
+}
+
+/// Ruta de la partida: sin nada, `SAVE_PATH` del perfil; un nombre a secas es una
+/// ranura del perfil (`nombre.json`); cualquier otra cosa (o `-`) es una ruta literal.
+fn slot_path(dir: Option<&str>, profile: &str, target: Option<&str>) -> String {
+    let file = match target {
+        None => SAVE_PATH.to_string(),
+        Some(t) if t != "-" && valid_name(t) => format!("{t}.json"),
+        Some(t) => return t.to_string(),
+    };
+    profile_dir(dir, profile).join(file).to_string_lossy().into_owned()
+}
+
+/// Nombre válido para perfiles y ranuras: letras, dígitos, `-` y `_`.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Tabla de puntuaciones, en la carpeta de perfiles (compartida por todos).
+const SCORES_FILE: &str = "scores.json";
+const TOP_SCORES: usize = 10;
+const POINTS_PER_ROOM: u32 = 10;
+const POINTS_FOR_WIN: u32 = 100;
+
+/// Una partida terminada en `SCORES_FILE`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScoreEntry {
+    player: String, // perfil
+    score: u32,
+    turns: u64,
+    outcome: String,
+    timestamp: u64, // segundos desde 1970
+    #[serde(default)]
+    assisted: bool,
+}
+
+/// Puntuaciones de `path`; sin archivo, ninguna.
+fn read_scores(path: &Path) -> Result<Vec<ScoreEntry>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("No se pudo leer {}: {e}", path.display()))?;
+    serde_json::from_str(&text).map_err(|_| anyhow!("{} está dañado", path.display()))
+}
+
+/// Cómo terminó la partida; `main` lo convierte en el código de salida del proceso.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Quit,   // 0
+    Win,    // 0
+    Death,  // 1
+    TimeUp, // 3 (el 2 es EXIT_ERROR)
+}
+
+impl Outcome {
+    fn name(self) -> &'static str {
+        match self {
+            Outcome::Quit => "abandonada",
+            Outcome::Win => "victoria",
+            Outcome::Death => "muerte",
+            Outcome::TimeUp => "sin tiempo",
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Quit | Outcome::Win => 0,
+            Outcome::Death => 1,
+            Outcome::TimeUp => 3,
+        }
+    }
+}
+
+/// Código de salida para errores irrecuperables (opciones o mundo no válidos).
+const EXIT_ERROR: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MenuChoice {
+    NewGame(Option<String>), // mundo elegido de worlds/, o el predeterminado
+    Continue,
+    Quit,
+}
+
+/// Carpeta donde `list` / `play` buscan mundos.
+const WORLDS_DIR: &str = "worlds";
+
+fn world_path(name: &str) -> String {
+    format!("{WORLDS_DIR}/{name}.json")
+}
+
+/// Nombres (sin extensión) de los `.json` de `worlds/`, ordenados.
+fn available_worlds() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(WORLDS_DIR) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+fn read_choice() -> Option<String> {
+    print!("\n> ");
+    io::stdout().flush().ok();
+    let mut buf = String::new();
+    match io::stdin().read_line(&mut buf) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(buf.trim().to_string()),
+    }
+}
+
+/// Menú de inicio. "Continuar" solo aparece si hay una partida guardada; si hay
+/// mundos en `worlds/`, "Nueva partida" pregunta cuál jugar.
+fn main_menu(worlds: &[String], save_path: &str) -> MenuChoice {
+    let has_save = Path::new(save_path).exists();
+    loop {
+        println!("=== MUD Offline ===");
+        println!("  1) Nueva partida");
+        if has_save {
+            println!("  2) Continuar");
+        }
+        println!("  3) Salir");
+        let Some(choice) = read_choice() else {
+            return MenuChoice::Quit;
+        };
+        match choice.as_str() {
+            "1" if worlds.is_empty() => return MenuChoice::NewGame(None),
+            "1" => {
+                if let Some(c) = choose_world(worlds) {
+                    return c;
+                }
+            }
+            "2" if has_save => return MenuChoice::Continue,
+            "3" => return MenuChoice::Quit,
+            _ => println!("Elige una opción del menú.\n"),
+        }
+    }
+}
+
+/// Submenú de mundos. `None` = volver al menú principal.
+fn choose_world(worlds: &[String]) -> Option<MenuChoice> {
+    loop {
+        println!("\nElige un mundo (vacío para volver):");
+        println!("  0) La cueva (mundo integrado)");
+        for (i, w) in worlds.iter().enumerate() {
+            println!("  {}) {w}", i + 1);
+        }
+        let Some(choice) = read_choice() else {
+            return Some(MenuChoice::Quit);
+        };
+        if choice.is_empty() {
+            println!();
+            return None;
+        }
+        match choice.parse::<usize>() {
+            Ok(0) => return Some(MenuChoice::NewGame(None)),
+            Ok(n) if n <= worlds.len() => return Some(MenuChoice::NewGame(Some(worlds[n - 1].clone()))),
+            _ => println!("Elige un número de la lista."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RoomState {
+    items: Vec<String>,
+    flags: HashMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ItemState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prototype: Option<String>, // para rehacer ejemplares creados en la partida (compras)
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    contents: Vec<String>,
+    #[serde(default)]
+    fuel: Option<u32>,
+    #[serde(default)]
+    fuel_warned: bool,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    identified: bool,
+    #[serde(default)]
+    searched: Vec<String>,
+    #[serde(default)]
+    examined: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NpcState {
+    location: String,
+    patrol_idx: usize,
+    #[serde(default)]
+    stock: Option<Vec<String>>,
+    #[serde(default)]
+    health: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SaveData {
+    player: Player,
+    #[serde(default)]
+    turns: u64,
+    #[serde(default)]
+    assisted: bool,
+    #[serde(default)]
+    turn_limit: Option<u64>,
+    #[serde(default)]
+    last_save_turn: Option<u64>, // sin él (partidas antiguas) se toma `turns`
+    rooms: HashMap<String, RoomState>,
+    #[serde(default)]
+    items: HashMap<String, ItemState>,
+    #[serde(default)]
+    npcs: HashMap<String, NpcState>,
+    #[serde(default)]
+    defeated: Vec<String>, // PNJs derrotados (ya no están en el mundo)
+    #[serde(default)]
+    version: u32, // 0 = anterior a `SAVE_VERSION`
+    #[serde(default)]
+    world_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ended: Option<Outcome>, // partida ya terminada (el autoguardado al cumplir el objetivo)
+}
+
+/// Formato de `SaveData` que escribe este motor; las partidas de una versión mayor
+/// no se cargan.
+const SAVE_VERSION: u32 = 1;
+
+/// Motivo por el que `save` no se puede cargar en `world`, sin mirar las salas: una
+/// versión demasiado nueva o el `world_id` de otro mundo (si los dos lo tienen).
+fn save_mismatch(world: &World, save: &SaveData) -> Option<String> {
+    if save.version > SAVE_VERSION {
+        return Some(format!("La partida es de la versión {} y este juego solo entiende hasta la {SAVE_VERSION}", save.version));
+    }
+    match (&world.world_id, &save.world_id) {
+        (Some(w), Some(s)) if w != s => Some(format!("La partida es del mundo '{s}', no de '{w}'")),
+        _ => None,
+    }
+}
+
+/// Resumen de una partida guardada para `saveinfo` / `save-info`, sin cargarla. Los
+/// puntos no cuentan la victoria, que no se guarda.
+fn save_summary(save: &SaveData) -> Vec<String> {
+    let p = &save.player;
+    let mut lines = vec![
+        format!("Jugador: {}", p.name),
+        format!("Sala: {}", p.location),
+        format!("Turnos: {}", save.turns),
+        format!("Salud: {}/{}", p.health, p.max_health),
+        format!("Oro: {}", p.gold),
+        format!("Salas visitadas: {}", p.visited.len()),
+        format!("Puntos: {}", p.visited.len() as u32 * POINTS_PER_ROOM + p.gold),
+    ];
+    if let Some(id) = &save.world_id {
+        lines.push(format!("Mundo: {id}"));
+    }
+    lines.push(match save.version {
+        0 => "Formato: antiguo (anterior a las versiones); se puede cargar".to_string(),
+        v if v > SAVE_VERSION => format!("Formato: versión {v}, demasiado nueva para este juego"),
+        v => format!("Formato: versión {v}"),
+    });
+    if save.assisted {
+        lines.push("Partida asistida".to_string());
+    }
+    lines
+}
+
+/// Subcomando `save-info`: resumen de la partida `path`. Sale con 0 si se ha podido
+/// leer y con `EXIT_ERROR` si no.
+fn save_info(path: &str) -> i32 {
+    match read_save(path) {
+        Ok(save) => {
+            for line in save_summary(&save) {
+                println!("{line}");
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("No se puede leer la partida {path}: {e}");
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Lee una partida de `path`, o un único documento JSON de la entrada estándar si
+/// `path` es "-".
+fn read_save(path: &str) -> Result<SaveData, GameError> {
+    if path == "-" {
+        return read_save_stream(io::stdin().lock());
+    }
+    if !Path::new(path).exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("No existe el archivo {path}")).into());
+    }
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| GameError::parse(Path::new(path), &e))
+}
+
+/// El primer documento JSON de `reader` (la entrada estándar con `load -`), sin leer
+/// más allá, para que lo que venga detrás se pueda seguir usando.
+fn read_save_stream(reader: impl io::Read) -> Result<SaveData, GameError> {
+    let mut stream = serde_json::Deserializer::from_reader(reader).into_iter();
+    match stream.next() {
+        Some(save) => save.map_err(|e| GameError::parse(Path::new("la entrada estándar"), &e)),
+        None => Err(GameError::InvalidSave("No hay partida en la entrada estándar".into())),
+    }
+}
+
+/// Todo lo que no encaja entre `save` y `world` (con todas sus salas cargadas). Los
+/// ejemplares creados en la partida (compras) valen si su plantilla existe.
+fn save_problems(world: &World, save: &SaveData) -> Vec<String> {
+    let exists = |key: &String| {
+        world.items.contains_key(key)
+            || save
+                .items
+                .get(key)
+                .and_then(|st| st.prototype.as_ref())
+                .is_some_and(|p| world.items.get(p).is_some_and(|it| it.template))
+    };
+    let mut out: Vec<String> = save_mismatch(world, save).into_iter().collect();
+    if !world.rooms.contains_key(&save.player.location) {
+        out.push(format!("el jugador está en la sala '{}', que no existe", save.player.location));
+    }
+    let mut rooms: Vec<(&String, &RoomState)> = save.rooms.iter().collect();
+    rooms.sort_by_key(|(k, _)| k.as_str());
+    for (key, st) in rooms {
+        if !world.rooms.contains_key(key) {
+            out.push(format!("la sala '{key}' no existe"));
+        }
+        for item in st.items.iter().filter(|k| !exists(k)) {
+            out.push(format!("la sala {key} contiene '{item}', que no existe"));
+        }
+    }
+    for item in save.player.inventory.iter().filter(|k| !exists(k)) {
+        out.push(format!("el inventario contiene '{item}', que no existe"));
+    }
+    let mut items: Vec<&String> = save.items.keys().filter(|k| !exists(k)).collect();
+    items.sort();
+    for key in items {
+        out.push(format!("hay estado guardado del objeto '{key}', que no existe"));
+    }
+    let mut npcs: Vec<&String> =
+        save.npcs.keys().chain(&save.defeated).filter(|k| !world.npcs.contains_key(*k)).collect();
+    npcs.sort();
+    for key in npcs {
+        out.push(format!("el PNJ '{key}' no existe"));
+    }
+    out
+}
+
+/// Línea "Salidas: ..." de una sala, marcando las salidas bloqueadas y con los
+/// nombres de dirección del mundo.
+fn exits_line(world: &World, room: &Room) -> String {
+    if room.exits.is_empty() {
+        return "Salidas: ninguna".into();
+    }
+    // orden fijo (el de las direcciones de siempre y luego alfabético): el de la tabla
+    // cambia de una ejecución a otra, y una misma semilla tiene que dar la misma salida
+    let mut dirs: Vec<&String> = room.exits.keys().collect();
+    dirs.sort_by_key(|d| (DIR_ABBREVIATIONS.iter().position(|(_, full)| full == d).unwrap_or(usize::MAX), d.as_str()));
+    let exits: Vec<String> = dirs
+        .into_iter()
+        .map(|dir| {
+            let label = world.dir_label(dir);
+            if room.flags.get(&format!("locked_{dir}")).copied().unwrap_or(false) {
+                format!("{label} (bloqueada)")
+            } else {
+                label.to_string()
+            }
+        })
+        .collect();
+    format!("Salidas: {}", exits.join(", "))
+}
+
+/// Abreviaturas de las direcciones de siempre, para `go n` o un `n` a secas.
+const DIR_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("n", "north"),
+    ("s", "south"),
+    ("e", "east"),
+    ("w", "west"),
+    ("u", "up"),
+    ("d", "down"),
+];
+
+fn opposite_dir(dir: &str) -> Option<&'static str> {
+    match dir {
+        "north" => Some("south"),
+        "south" => Some("north"),
+        "east" => Some("west"),
+        "west" => Some("east"),
+        "up" => Some("down"),
+        "down" => Some("up"),
+        _ => None,
+    }
+}
+
+/// Dirección de la salida de `room` que lleva a `dest`, si la hay.
+fn exit_towards<'a>(room: &'a Room, dest: &str) -> Option<&'a str> {
+    room.exits
+        .iter()
+        .find(|(_, to)| to.as_str() == dest)
+        .map(|(dir, _)| dir.as_str())
+}
+
+/// Generador pseudoaleatorio pequeño (splitmix64): la misma semilla da la misma secuencia.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Entero en `0..n` (`n` > 0).
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Semilla a partir del reloj, para cuando no se pasa --seed.
+fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Manejador de un efecto de `use`: recibe la clave del objeto y el valor del efecto y
+/// devuelve lo que hay que contar al jugador, sin imprimirlo (de eso se ocupa `cmd_use`).
+type EffectHandler = fn(&mut Game, &str, &str) -> Vec<String>;
+
+/// Efectos que responden a `use`, en orden de prioridad: si un objeto tiene varios,
+/// actúa el primero de la lista. Un efecto nuevo se añade aquí, no en `cmd_use`.
+/// Los pasivos (`poison`, `switch`, `text`, `damage`...) no necesitan entrada.
+const EFFECTS: &[(&str, EffectHandler)] = &[
+    ("lights", Game::effect_lights),
+    ("unlocks", Game::effect_unlocks),
+    ("heal", Game::effect_heal),
+    ("feed", Game::effect_heal),
+    ("identify", Game::effect_identify),
+];
+
+/// Descripción de un efecto para `examine` de un objeto ya identificado.
+fn effect_label(effect: &str, value: &str) -> Option<String> {
+    Some(match effect {
+        "lights" => "da luz".into(),
+        "heal" => format!("cura {value} puntos"),
+        "feed" => format!("alimenta ({value} puntos)"),
+        "poison" => format!("envenena ({value} por turno)"),
+        "unlocks" => "abre algo".into(),
+        "identify" => "revela la naturaleza de las cosas".into(),
+        _ => return None,
+    })
+}
+
+/// Notas que caben en el cuaderno.
+const MAX_NOTES: usize = 50;
+
+/// Pausa por defecto entre órdenes de `--demo`.
+const DEMO_DELAY: Duration = Duration::from_millis(1500);
+
+/// Guion de `--demo`: una orden por línea; se saltan las vacías y las que empiezan
+/// por `#`.
+fn read_demo(path: &str) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("No se pudo leer {path}: {e}"))?;
+    let commands: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect();
+    if commands.is_empty() {
+        return Err(anyhow!("El guion {path} no tiene órdenes"));
+    }
+    Ok(commands)
+}
+
+/// Eventos que admite --autosave-on: entrar por primera vez en una sala, cumplir el
+/// objetivo (`win`) y derrotar a un PNJ.
+const AUTOSAVE_EVENTS: &[&str] = &["enter", "goal", "defeat"];
+
+/// Ranura del perfil donde escribe el autoguardado.
+const AUTOSAVE_SLOT: &str = "autosave";
+
+/// Puntos de control que caben en una sesión.
+const MAX_CHECKPOINTS: usize = 10;
+
+/// Máximo de salas por `go <dir> <n>`.
+const MAX_GO_STEPS: usize = 10;
+
+/// Palabras de relleno que se descartan de la entrada ("go to the north").
+const STOP_WORDS: &[&str] = &["to", "the", "a", "el", "la", "hacia", "al"];
+
+/// Bytes que se guardan de una línea de entrada; el resto se descarta.
+const MAX_INPUT_LEN: usize = 4096;
+
+/// Lee una línea de `input` (la entrada estándar, en el juego) guardando como mucho
+/// `MAX_INPUT_LEN` bytes. Devuelve los bytes leídos (0 = fin de la entrada) y si la
+/// línea se ha truncado.
+fn read_input_line(input: &mut impl BufRead, buf: &mut String) -> io::Result<(usize, bool)> {
+    let mut bytes = Vec::new();
+    let n = input.take(MAX_INPUT_LEN as u64).read_until(b'\n', &mut bytes)?;
+    let truncated = n == MAX_INPUT_LEN && bytes.last() != Some(&b'\n');
+    if truncated {
+        input.skip_until(b'\n')?;
+    }
+    // el corte puede dejar un carácter a medias, que se pierde
+    buf.push_str(String::from_utf8_lossy(&bytes).trim_end_matches('\u{FFFD}'));
+    Ok((n, truncated))
+}
+
+/// Respuesta por defecto a un comando desconocido (ver `World::unknown_command_message`).
+const UNKNOWN_COMMAND: &str = "No entiendo ese comando. Escribe 'help'.";
+
+/// Lo único que se puede hacer con la partida terminada.
+const META_VERBS: &[&str] = &["restart", "load", "rewind", "score", "scores", "quit"];
+
+/// Un mismo comando fallido repetido estas veces entre los últimos `NUDGE_HISTORY`
+/// fallos da una pista suave (salvo con --no-nudge).
+const NUDGE_AFTER: usize = 3;
+const NUDGE_HISTORY: usize = 5;
+const NUDGE_LOCKED: &str = "Quizás necesites algo para pasar.";
+
+/// Verbos del jugador, para `verbs`; los de depuración no aparecen. Ha de coincidir
+/// con los brazos de `run_command` (lo comprueba un test).
+const VERBS: &[&str] = &[
+    "look", "exits", "feel", "go", "climb", "enter", "exit", "travel", "take", "put", "open", "close", "lock", "unlock", "drop", "use",
+    "apply", "light", "extinguish", "examine", "examinarme", "read", "inventory", "wield", "attack", "buy",
+    "sell", "save", "load", "saves", "saveinfo", "profile", "status", "names", "note", "score", "restart", "checkpoint",
+    "rewind", "checkpoints", "scores", "transcript", "map", "about", "seed", "solution", "verbs", "help", "quit",
+];
+
+/// Sinónimos y abreviaturas de cada verbo, resueltos antes de despachar.
+const VERB_ALIASES: &[(&str, &str)] = &[
+    ("l", "look"),
+    ("salidas", "exits"),
+    ("palpar", "feel"),
+    ("tocar", "feel"),
+    ("g", "go"),
+    ("get", "take"),
+    ("bloquear", "lock"),
+    ("desbloquear", "unlock"),
+    ("abrir", "open"),
+    ("cerrar", "close"),
+    ("x", "examine"),
+    ("examinar", "examine"),
+    ("leer", "read"),
+    ("inv", "inventory"),
+    ("estado", "status"),
+    ("solucion", "solution"),
+    ("trepar", "climb"),
+    ("entrar", "enter"),
+    ("salir", "exit"),
+    ("version", "about"),
+    ("comprar", "buy"),
+    ("notes", "note"),
+    ("apuntar", "note"),
+    ("encender", "light"),
+    ("apagar", "extinguish"),
+    ("mapa", "map"),
+    ("meter", "put"),
+    ("aplicar", "apply"),
+    ("empuñar", "wield"),
+    ("atacar", "attack"),
+    ("reiniciar", "restart"),
+    ("puntuacion", "score"),
+    ("puntuaciones", "scores"),
+    ("viajar", "travel"),
+    ("nombres", "names"),
+    ("perfil", "profile"),
+    ("partidas", "saves"),
+    ("vender", "sell"),
+];
+
+/// Mismas palabras sin contar mayúsculas ni las de relleno ("Entrada de la Cueva" y
+/// "entrada de cueva"), que el jugador puede escribir o no.
+fn same_words(a: &str, b: &str) -> bool {
+    let words = |s: &str| -> Vec<String> {
+        s.split_whitespace()
+            .map(str::to_lowercase)
+            .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+            .collect()
+    };
+    words(a) == words(b)
+}
+
+/// Verbo canónico de `cmd` (ya en minúsculas); los desconocidos se devuelven tal cual.
+fn canonical_verb(cmd: &str) -> &str {
+    VERB_ALIASES.iter().find(|(alias, _)| *alias == cmd).map_or(cmd, |(_, verb)| verb)
+}
+
+/// Verbos cuyo argumento es texto libre y se pasa tal cual.
+const RAW_TEXT_VERBS: &[&str] = &["setdesc", "set", "save", "load", "saveinfo", "checkpoint", "rewind", "note", "notes", "apuntar", "transcript"];
+
+/// Palabras de relleno que `apply` necesita para separar herramienta y objeto.
+const APPLY_SEPARATORS: &[&str] = &["to", "a"];
+
+/// Palabras que abren un complemento ("take llave from the cofre"); el relleno que
+/// las sigue se descarta igual que el que sigue al verbo.
+const PHRASE_STARTS: &[&str] = &["from", "in", "en", "under", "behind", "debajo", "detras", "detrás"];
+
+/// Quita la puntuación de los extremos de cada palabra y las palabras de relleno que
+/// siguen al verbo o a una de `PHRASE_STARTS`; en medio de un nombre se conservan
+/// ("llave de la reja", "capa a rayas"). Los comandos de texto libre no se tocan, y lo
+/// entrecomillado tampoco: se conserva con sus comillas para que `split_on` lo respete.
+fn normalize_input(line: &str) -> String {
+    let Some(verb) = line.split_whitespace().next() else {
+        return String::new();
+    };
+    if RAW_TEXT_VERBS.contains(&verb.to_lowercase().as_str()) {
+        return line.to_string();
+    }
+    let is_punct = |c: char| matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '¡' | '¿');
+    let mut words = tokenize(line).into_iter();
+    let Some((verb, _)) = words.next() else {
+        return String::new();
+    };
+    let kept: &[&str] = if canonical_verb(&verb.to_lowercase()) == "apply" { APPLY_SEPARATORS } else { &[] };
+    let mut out = vec![verb.trim_matches(is_punct).to_string()];
+    // el relleno solo se quita al empezar un complemento, justo tras el verbo o el separador
+    let mut phrase_start = true;
+    for (w, quoted) in words {
+        if quoted {
+            out.push(format!("\"{w}\""));
+            phrase_start = false;
+            continue;
+        }
+        let w = w.trim_matches(is_punct);
+        let lower = w.to_lowercase();
+        if w.is_empty() {
+            continue;
+        }
+        if kept.contains(&lower.as_str()) || PHRASE_STARTS.contains(&lower.as_str()) {
+            phrase_start = true;
+        } else if phrase_start && STOP_WORDS.contains(&lower.as_str()) {
+            continue;
+        } else {
+            phrase_start = false;
+        }
+        out.push(w.to_string());
+    }
+    out.join(" ")
+}
+
+/// Palabras de `line`; un tramo entre comillas dobles es una sola palabra (sin las
+/// comillas). Cada palabra indica si iba entrecomillada. Una comilla sin cerrar
+/// abarca hasta el final.
+fn tokenize(line: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        if c == '"' {
+            if !cur.is_empty() || quoted {
+                tokens.push((std::mem::take(&mut cur), quoted));
+            }
+            quoted = !quoted;
+        } else if c.is_whitespace() && !quoted {
+            if !cur.is_empty() {
+                tokens.push((std::mem::take(&mut cur), false));
+            }
+        } else {
+            cur.push(c);
+        }
+    }
+    if !cur.is_empty() {
+        tokens.push((cur, quoted));
+    }
+    tokens.retain(|(w, _)| !w.trim().is_empty());
+    tokens
+}
+
+/// Parte `rest` por la primera palabra `sep` que no vaya entre comillas
+/// (`"caja de madera" from "baúl"`). Ambos lados han de tener algo.
+fn split_on(rest: &str, sep: &str) -> Option<(String, String)> {
+    let tokens = tokenize(rest);
+    let pos = tokens.iter().position(|(w, quoted)| !quoted && w.eq_ignore_ascii_case(sep))?;
+    let join = |ts: &[(String, bool)]| ts.iter().map(|(w, _)| w.as_str()).collect::<Vec<_>>().join(" ");
+    let (left, right) = (join(&tokens[..pos]), join(&tokens[pos + 1..]));
+    (!left.is_empty() && !right.is_empty()).then_some((left, right))
+}
+
+/// Ancho por defecto cuando no hay terminal o no se puede detectar.
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_HEIGHT: usize = 24;
+
+/// Ancho de texto para toda la presentación, por orden: `--width`, la variable
+/// `COLUMNS`, el tamaño real de la terminal y, si nada de eso sirve, `DEFAULT_WIDTH`.
+/// Con la salida redirigida y sin las dos primeras, el resultado es siempre el mismo.
+fn resolve_width(cli: Option<usize>) -> usize {
+    pick_width(cli, std::env::var("COLUMNS").ok().as_deref(), terminal_columns)
+}
+
+/// `resolve_width` con las fuentes a la vista; la terminal solo se consulta si
+/// hace falta.
+fn pick_width(cli: Option<usize>, columns: Option<&str>, terminal: impl FnOnce() -> Option<usize>) -> usize {
+    cli.filter(|&w| w > 0)
+        .or_else(|| columns.and_then(|c| c.trim().parse().ok()).filter(|&w| w > 0))
+        .or_else(terminal)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Filas y columnas de la terminal, si la salida es una terminal; los ceros no
+/// cuentan como tamaño.
+fn terminal_dimensions() -> Option<(usize, usize)> {
+    let (Width(cols), Height(rows)) = terminal_size::terminal_size()?;
+    (rows > 0 && cols > 0).then_some((usize::from(rows), usize::from(cols)))
+}
+
+/// Columnas de la terminal, si la salida es una terminal.
+fn terminal_columns() -> Option<usize> {
+    terminal_dimensions().map(|(_, cols)| cols)
+}
+
+/// Filas de la terminal para paginar, o `None` si la entrada o la salida no son una terminal.
+/// Como el ancho: la variable `LINES`, el tamaño real de la terminal y, si no, `DEFAULT_HEIGHT`.
+fn terminal_height() -> Option<usize> {
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        return None;
+    }
+    let rows = std::env::var("LINES")
+        .ok()
+        .and_then(|c| c.trim().parse().ok())
+        .filter(|&h| h > 0)
+        .or_else(|| terminal_dimensions().map(|(rows, _)| rows))
+        .unwrap_or(DEFAULT_HEIGHT);
+    Some(rows)
+}
+
+/// Ajusta `text` a `width` columnas. Los saltos de línea del autor se respetan como párrafos.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for para in text.split('\n') {
+        let mut line = String::new();
+        let mut len = 0;
+        for word in para.split_whitespace() {
+            let wlen = word.chars().count();
+            if len > 0 && len + 1 + wlen > width {
+                lines.push(std::mem::take(&mut line));
+                len = 0;
+            }
+            if len > 0 {
+                line.push(' ');
+                len += 1;
+            }
+            line.push_str(word);
+            len += wlen;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Distancia de Levenshtein entre dos cadenas, por caracteres.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Dirección para frases como "se marcha hacia ...": el nombre que le da el mundo
+/// (`names` es su `direction_names`) o el castellano.
+fn dir_phrase(names: &HashMap<String, String>, dir: &str) -> Option<String> {
+    match names.get(dir) {
+        Some(label) => Some(label.clone()),
+        None => dir_es(dir).map(String::from),
+    }
+}
+
+/// Nombre en castellano de una dirección, con artículo cuando lo lleva ("el norte", "arriba").
+fn dir_es(dir: &str) -> Option<&'static str> {
+    match dir {
+        "north" => Some("el norte"),
+        "south" => Some("el sur"),
+        "east" => Some("el este"),
+        "west" => Some("el oeste"),
+        "up" => Some("arriba"),
+        "down" => Some("abajo"),
+        _ => None,
+    }
+}
+
+/// Desplazamiento en la rejilla del mapa de las direcciones cardinales.
+fn grid_step(dir: &str) -> Option<(i32, i32)> {
+    match dir {
+        "north" => Some((0, -1)),
+        "south" => Some((0, 1)),
+        "east" => Some((1, 0)),
+        "west" => Some((-1, 0)),
+        _ => None,
+    }
+}
+
+/// Coloca `rooms` en rejillas, una por nivel: las salidas cardinales dan la posición y
+/// cada sala que no encaja (por `up`/`down` o por choque) empieza un nivel nuevo.
+fn map_layout(world: &World, rooms: &[String]) -> Vec<HashMap<(i32, i32), String>> {
+    let mut placed: Vec<&String> = Vec::new();
+    let mut levels = Vec::new();
+    for first in rooms {
+        if placed.contains(&first) {
+            continue;
+        }
+        let mut grid = HashMap::from([((0, 0), first.clone())]);
+        let mut queue = std::collections::VecDeque::from([(first, (0, 0))]);
+        placed.push(first);
+        while let Some((key, (x, y))) = queue.pop_front() {
+            let mut exits: Vec<(&String, &String)> = world.rooms[key].exits.iter().collect();
+            exits.sort();
+            for (dir, dest) in exits {
+                let Some((dx, dy)) = grid_step(dir) else {
+                    continue;
+                };
+                let Some(dest) = rooms.iter().find(|r| *r == dest) else {
+                    continue;
+                };
+                let pos = (x + dx, y + dy);
+                if !placed.contains(&dest) && !grid.contains_key(&pos) {
+                    grid.insert(pos, dest.clone());
+                    placed.push(dest);
+                    queue.push_back((dest, pos));
+                }
+            }
+        }
+        levels.push(grid);
+    }
+    levels
+}
+
+/// Dibuja un nivel de `map_layout`; `label` da el texto de cada casilla (hasta 5 letras).
+fn render_level(world: &World, grid: &HashMap<(i32, i32), String>, label: impl Fn(&str) -> String) -> Vec<String> {
+    let xs = grid.keys().map(|p| p.0);
+    let ys = grid.keys().map(|p| p.1);
+    let (x0, x1) = (xs.clone().min().unwrap_or(0), xs.max().unwrap_or(0));
+    let (y0, y1) = (ys.clone().min().unwrap_or(0), ys.max().unwrap_or(0));
+    let linked = |a: Option<&String>, dir: &str, b: Option<&String>| match (a, b) {
+        (Some(a), Some(b)) => world.rooms[a].exits.get(dir) == Some(b),
+        _ => false,
+    };
+    let mut lines = Vec::new();
+    for y in y0..=y1 {
+        let mut row = String::new();
+        let mut below = String::new();
+        for x in x0..=x1 {
+            let here = grid.get(&(x, y));
+            match here {
+                Some(key) => row.push_str(&format!("[{:^5}]", label(key))),
+                None => row.push_str("       "),
+            }
+            let east = grid.get(&(x + 1, y));
+            if x < x1 {
+                row.push_str(if linked(here, "east", east) || linked(east, "west", here) { "--" } else { "  " });
+            }
+            let south = grid.get(&(x, y + 1));
+            let vertical = linked(here, "south", south) || linked(south, "north", here);
+            below.push_str(if vertical { "   |     " } else { "         " });
+        }
+        lines.push(row.trim_end().to_string());
+        if y < y1 {
+            lines.push(below.trim_end().to_string());
+        }
+    }
+    lines
+}
+
+/// El mundo integrado: la cueva.
+pub fn build_world() -> World {
+    // Items
+    let mut items = IndexMap::new();
+    items.insert(
+        "torch".into(),
+        Item {
+            key: "torch".into(),
+            name: "antorcha".into(),
+            desc: "Una antorcha de madera. Aporta luz.".into(),
+            portable: true,
+            effects: HashMap::from([("lights".into(), "true".into())]),
+            gender: Some("f".into()),
+            fuel: Some(40),
+            state: "unlit".into(),
+            state_desc: HashMap::from([
+                ("unlit".into(), "Una antorcha de madera, apagada. Podrías encenderla.".into()),
+                ("lit".into(), "Una antorcha de madera. Arde con una llama viva.".into()),
+            ]),
+            state_names: HashMap::from([("lit".into(), "antorcha encendida".into())]),
+            shuffle: true,
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "key_gate".into(),
+        Item {
+            key: "key_gate".into(),
+            name: "llave vieja".into(),
+            desc: "Una llave oxidada con una runa.".into(),
+            portable: true,
+            effects: HashMap::from([("unlocks".into(), "narrow_passage:north".into())]),
+            gender: Some("f".into()),
+            shuffle: true,
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "note".into(),
+        Item {
+            key: "note".into(),
+            name: "nota arrugada".into(),
+            desc: "Dice: 'La luz revela lo que temes.'".into(),
+            portable: true,
+            effects: HashMap::from([("readable".into(), "true".into())]),
+            gender: Some("f".into()),
+            shuffle: true,
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "lever".into(),
+        Item {
+            key: "lever".into(),
+            name: "palanca".into(),
+            desc: "Una palanca de hierro encajada en la roca.".into(),
+            portable: false,
+            effects: HashMap::from([
+                ("switch".into(), "true".into()),
+                ("unlocks".into(), "ancient_chamber:down".into()),
+            ]),
+            gender: Some("f".into()),
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "amulet".into(),
+        Item {
+            key: "amulet".into(),
+            name: "amuleto negro".into(),
+            desc: "Un amuleto de obsidiana, frío al tacto. Cuesta soltarlo.".into(),
+            portable: true,
+            effects: HashMap::from([("poison".into(), "4".into())]),
+            gender: Some("m".into()),
+            unidentified_desc: Some("Un amuleto de piedra negra. No sabrías decir para qué sirve.".into()),
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "potion".into(),
+        Item {
+            key: "potion".into(),
+            name: "poción roja".into(),
+            desc: "Un frasquito con un líquido espeso y rojo. Huele a hierbas.".into(),
+            portable: true,
+            effects: HashMap::from([("heal".into(), "30".into())]),
+            gender: Some("f".into()),
+            consumable: true,
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "chest".into(),
+        Item {
+            key: "chest".into(),
+            name: "cofre".into(),
+            desc: "Un cofre de madera con herrajes de bronce.".into(),
+            portable: false,
+            effects: HashMap::new(),
+            container: true,
+            closed: true,
+            contents: vec!["amulet".into(), "potion".into()],
+            gender: Some("m".into()),
+            ..Default::default()
+        },
+    );
+    items.insert(
+        "altar".into(),
+        Item {
+            key: "altar".into(),
+            name: "altar de piedra".into(),
+            desc: "Un altar frío y pesado. No puedes cargarlo.".into(),
+            portable: false,
+            effects: HashMap::new(),
+            gender: Some("m".into()),
+            ..Default::default()
+        },
+    );
+
+    // Rooms
+    let cave_entrance = Room {
+        key: "cave_entrance".into(),
+        name: "Entrada de la Cueva".into(),
+        desc: "El viento helado sopla tras de ti. Un pasaje oscuro se interna hacia el norte.".into(),
+        exits: HashMap::from([("north".into(), "narrow_passage".into())]),
+        items: vec!["note".into(), "torch".into(), "lever".into()],
+        flags: HashMap::new(),
+        no_drop_message: None,
+        requires: HashMap::new(),
+        first_cross_message: HashMap::new(),
+        sounds: vec![],
+    };
+    let narrow_passage = Room {
+        key: "narrow_passage".into(),
+        name: "Pasadizo Estrecho".into(),
+        desc: "Las paredes se cierran a tu alrededor. Al norte ves una reja; al sur, la salida.".into(),
+        exits: HashMap::from([
+            ("south".into(), "cave_entrance".into()),
+            ("north".into(), "ancient_chamber".into()),
+        ]),
+        items: vec!["key_gate".into()],
+        flags: HashMap::from([
+            ("dark".into(), true),
+            ("locked_north".into(), true),
+            ("water".into(), true),
+        ]),
+        no_drop_message: None,
+        requires: HashMap::new(),
+        first_cross_message: HashMap::new(),
+        sounds: vec![("water".into(), "Oyes agua goteando en algún lugar.".into())],
+    };
+    let ancient_chamber = Room {
+        key: "ancient_chamber".into(),
+        name: "Cámara Ancestral".into(),
+        desc: "Una sala amplia con grabados antiguos. Un altar domina el centro; tras él, una trampilla.".into(),
+        exits: HashMap::from([
+            ("south".into(), "narrow_passage".into()),
+            ("down".into(), "crypt".into()),
+        ]),
+        items: vec!["altar".into()],
+        flags: HashMap::from([("no_drop".into(), true), ("locked_down".into(), true)]),
+        no_drop_message: Some("Es un lugar sagrado. No te atreves a dejar nada aquí.".into()),
+        requires: HashMap::new(),
+        first_cross_message: HashMap::new(),
+        sounds: vec![],
+    };
+    let crypt = Room {
+        key: "crypt".into(),
+        name: "Cripta".into(),
+        desc: "Un recinto bajo y polvoriento. Nichos vacíos recorren las paredes.".into(),
+        exits: HashMap::from([("up".into(), "ancient_chamber".into())]),
+        items: vec!["chest".into()],
+        flags: HashMap::new(),
+        no_drop_message: None,
+        requires: HashMap::new(),
+        first_cross_message: HashMap::new(),
+        sounds: vec![],
+    };
+
+    let mut rooms = IndexMap::new();
+    rooms.insert(cave_entrance.key.clone(), cave_entrance);
+    rooms.insert(narrow_passage.key.clone(), narrow_passage);
+    rooms.insert(ancient_chamber.key.clone(), ancient_chamber);
+    rooms.insert(crypt.key.clone(), crypt);
+
+    // PNJs
+    let mut npcs = IndexMap::new();
+    npcs.insert(
+        "gatekeeper".into(),
+        Npc {
+            key: "gatekeeper".into(),
+            name: "el guardián".into(),
+            desc: "Un anciano encapuchado que vigila la reja.".into(),
+            location: "ancient_chamber".into(),
+            patrol: vec!["ancient_chamber".into(), "narrow_passage".into()],
+            patrol_idx: 0,
+            desc_variants: vec![(
+                "locked_north".into(),
+                "Un anciano encapuchado que se planta ante la reja y bloquea el paso.".into(),
+            )],
+            sells: HashMap::new(),
+            buys: HashMap::new(),
+            stock: None,
+            health: None,
+            attack: 0,
+            items: vec![],
+        },
+    );
+
+    World {
+        title: Some("La cueva del guardián".into()),
+        world_id: Some("builtin".into()),
+        rooms,
+        items,
+        npcs,
+        include: vec![],
+        start: default_start(),
+        auto_reverse: false,
+        dark_blocks_movement: false,
+        max_items: None,
+        full_message: None,
+        unknown_command_message: None,
+        look_item_limit: None,
+        flag_patterns: vec![],
+        opposites: HashMap::new(),
+        direction_names: HashMap::new(),
+        walkthrough: vec![
+            "take torch".into(),
+            "use torch".into(),
+            "use palanca".into(),
+            "go north".into(),
+            "take key_gate".into(),
+            "go north".into(),
+            "go down".into(),
+            "open cofre".into(),
+        ],
+        turn_limit: None,
+        difficulty: None,
+        difficulty_overrides: DifficultyOverrides::default(),
+        win: None,
+        endings: vec![],
+        room_dir: None,
+    }
+}
+
+/// Carga el mundo (de archivo o el integrado) y aplica las opciones que lo transforman.
+fn prepare_world(path: Option<&str>, opts: &Options) -> Result<World> {
+    let mut world = match path {
+        Some(path) => World::load_from_file(path)?,
+        None => build_world(),
+    };
+    // barajar y crear salidas de vuelta recorren el mapa entero, así que un mundo por
+    // partes se lee completo; si no, sus salas se leen al entrar y los avisos sobre el
+    // mundo entero quedan para `validate`
+    if opts.shuffle || world.auto_reverse || opts.auto_reverse {
+        world.load_all_rooms()?;
+    }
+    if world.room_dir.is_none() || opts.shuffle || world.auto_reverse || opts.auto_reverse {
+        let warnings = world.opposite_warnings().into_iter().chain(world.item_location_warnings()).chain(world.flag_warnings());
+        for w in warnings {
+            eprintln!("Aviso: {w}");
+        }
+    }
+    if world.auto_reverse || opts.auto_reverse {
+        let links = world.link_reverse_exits();
+        if !links.is_empty() {
+            println!("Salidas de vuelta creadas automáticamente:");
+            for l in links {
+                println!("  {l}");
+            }
+        }
+    }
+    if opts.shuffle {
+        let mut rng = Rng::new(opts.seed.unwrap_or_else(time_seed));
+        world.shuffle_items(&mut rng);
+    }
+    Ok(world)
+}
+
+/// Subcomando `check-save`: comprueba, sin jugar, que la partida `path` encaja con el
+/// mundo (`--world`, `$MUD_WORLD` o el integrado). Sale con 0 si encaja, 1 si no y
+/// `EXIT_ERROR` si no se puede leer alguno de los dos.
+fn check_save(path: &str, world: Option<&str>) -> i32 {
+    let world = match world {
+        Some(w) => World::load_from_file(w).and_then(|mut w| w.load_all_rooms().map(|_| w)).map_err(anyhow::Error::from),
+        None => Ok(build_world()),
+    };
+    let (world, save) = match world.and_then(|w| Ok((w, read_save(path)?))) {
+        Ok(pair) => pair,
+        Err(e) => {
+            eprintln!("{e}");
+            return EXIT_ERROR;
+        }
+    };
+    let problems = save_problems(&world, &save);
+    for p in &problems {
+        println!("FALLO: {p}");
+    }
+    let title = world.title.as_deref().unwrap_or("(sin título)");
+    if problems.is_empty() {
+        println!("{path}: partida válida para {title}");
+    } else {
+        println!("{path}: {} problemas con {title}", problems.len());
+    }
+    i32::from(!problems.is_empty())
+}
+
+/// Subcomando `validate`: informe de una línea por hallazgo ("ERROR categoría: ...",
+/// "AVISO categoría: ...") y un resumen. Devuelve el código de salida: 0 sin errores,
+/// 1 con errores, `EXIT_ERROR` si ni siquiera se puede leer.
+fn validate_world(path: &str) -> i32 {
+    let read = if Path::new(path).is_dir() {
+        World::load_from_dir(Path::new(path)).and_then(|mut w| w.load_all_rooms().map(|_| w)).map_err(anyhow::Error::from)
+    } else {
+        World::read_with_includes(Path::new(path), &mut Vec::new()).map_err(anyhow::Error::from).map(|mut w| {
+            w.instantiate_all();
+            w
+        })
+    };
+    let world = match read {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("{e}");
+            return EXIT_ERROR;
+        }
+    };
+    let findings = world.validate();
+    let errors = findings.iter().filter(|f| f.error).count();
+    for f in findings.iter().filter(|f| f.error).chain(findings.iter().filter(|f| !f.error)) {
+        println!("{} {}: {}", if f.error { "ERROR" } else { "AVISO" }, f.category, f.message);
+    }
+    println!("{path}: {errors} errores, {} avisos", findings.len() - errors);
+    i32::from(errors > 0)
+}
+
+/// Subcomando `bench-load <dir>`: compara el tiempo de arranque de un mundo por partes
+/// leyendo todas las salas de golpe o solo la inicial, como hace el juego.
+fn bench_load(dir: &str) -> Result<()> {
+    const RUNS: u32 = 5;
+    let time = |lazy: bool| -> Result<(std::time::Duration, usize)> {
+        let start = std::time::Instant::now();
+        let mut rooms = 0;
+        for _ in 0..RUNS {
+            let mut world = World::load_from_dir(Path::new(dir))?;
+            if lazy {
+                let store = LazyDirStore { dir: world.room_dir.clone().unwrap_or_default() };
+                if let Some(room) = store.fetch(&world.start)? {
+                    world.rooms.insert(world.start.clone(), room);
+                }
+            } else {
+                world.load_all_rooms()?;
+            }
+            rooms = world.rooms.len();
+        }
+        Ok((start.elapsed() / RUNS, rooms))
+    };
+    let (eager, all) = time(false)?;
+    let (lazy, loaded) = time(true)?;
+    println!("todas las salas:   {:>10.3} ms ({all} salas)", eager.as_secs_f64() * 1000.0);
+    println!("solo la inicial:   {:>10.3} ms ({loaded} salas)", lazy.as_secs_f64() * 1000.0);
+    Ok(())
+}
+
+/// Punto de entrada del binario: lee los argumentos y juega (o corre el subcomando).
+pub fn run() {
+    let mut opts = match Options::from_args() {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    if let Some(path) = &opts.transcript {
+        if let Err(e) = start_transcript(path) {
+            eprintln!("{e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+    if let Some(dir) = &opts.bench_load {
+        if let Err(e) = bench_load(dir) {
+            eprintln!("{e}");
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+    if let Some(path) = &opts.save_info {
+        std::process::exit(save_info(path));
+    }
+    if let Some(path) = &opts.validate {
+        std::process::exit(validate_world(path));
+    }
+    if let Some(path) = &opts.check_save {
+        std::process::exit(check_save(path, opts.world.as_deref()));
+    }
+    if opts.list {
+        let worlds = available_worlds();
+        if worlds.is_empty() {
+            println!("No hay mundos en {WORLDS_DIR}/.");
+        } else {
+            println!("Mundos disponibles:");
+            for w in worlds {
+                println!("  {w}");
+            }
+        }
+        return;
+    }
+    // el menú solo tiene sentido en una sesión interactiva
+    let show_menu = !opts.no_menu && opts.demo.is_none() && io::stdin().is_terminal();
+    let worlds = if opts.world.is_some() { vec![] } else { available_worlds() };
+    let profile = opts.profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+    let save_path = slot_path(opts.save_dir.as_deref(), profile, None);
+    let choice = if show_menu { main_menu(&worlds, &save_path) } else { MenuChoice::NewGame(None) };
+    let path = match &choice {
+        MenuChoice::Quit => return,
+        MenuChoice::NewGame(Some(name)) => Some(world_path(name)),
+        _ => opts.world.clone(),
+    };
+    // sin --seed se elige una y se muestra, para poder repetir la partida
+    if opts.shuffle && opts.seed.is_none() {
+        opts.seed = Some(time_seed());
+    }
+    let world = match prepare_world(path.as_deref(), &opts) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    let demo = match opts.demo.as_deref().map(read_demo).transpose() {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    let delay = opts.delay.unwrap_or(DEMO_DELAY);
+    let mut game = Game::new(world, opts);
+    if choice == MenuChoice::Continue {
+        if let Err(e) = game.load(&save_path) {
+            println!("{e}");
+            game.intro();
+        }
+    } else {
+        game.intro();
+    }
+    if let Some(commands) = demo {
+        game.run_demo(&commands, delay);
+    }
+    let outcome = game.loop_run();
+    std::process::exit(outcome.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Carpeta temporal propia de una prueba, vacía al empezar.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mud_offline_{}_{name}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Opciones de una partida de prueba: ancho fijo, sin paginar y con las partidas
+    /// y puntuaciones en una carpeta propia.
+    fn opts(name: &str) -> Options {
+        Options {
+            lang: LANGUAGES[0],
+            width: Some(80),
+            no_pager: true,
+            save_dir: Some(temp_dir(name).to_string_lossy().into_owned()),
+            ..Options::default()
+        }
+    }
+
+    /// Partida de prueba; las preguntas nunca esperan a la terminal (sin respuesta
+    /// preparada, se contestan con una línea vacía).
+    fn game(world: World, name: &str) -> Game {
+        let mut g = Game::new(world, opts(name));
+        g.replies = Some(Default::default());
+        g
+    }
+
+    /// Partida en el mundo integrado, con el jugador puesto en `room`.
+    fn builtin_at(room: &str, name: &str) -> Game {
+        let mut g = game(build_world(), name);
+        g.player.location = room.to_string();
+        g
+    }
+
+    /// Lo que se ha impreso desde la última llamada.
+    fn output() -> String {
+        CAPTURED.with(|c| std::mem::take(&mut *c.borrow_mut()))
+    }
+
+    /// Ejecuta las órdenes como si se tecleasen y devuelve lo que imprimen.
+    fn run(g: &mut Game, commands: &[&str]) -> String {
+        output();
+        for c in commands {
+            g.run_command(c);
+        }
+        output()
+    }
+
+    /// Pone `key` en el inventario, sacándolo de salas y recipientes.
+    fn give(g: &mut Game, key: &str) {
+        for r in g.world.rooms.values_mut() {
+            r.items.retain(|k| k != key);
+        }
+        for it in g.world.items.values_mut() {
+            it.contents.retain(|k| k != key);
+        }
+        g.player.inventory.push(key.to_string());
+    }
+
+    fn carries(g: &Game, key: &str) -> bool {
+        g.player.inventory.iter().any(|k| k == key)
+    }
+
+    #[test]
+    fn drop_is_refused_in_no_drop_room_and_allowed_elsewhere() {
+        let mut g = builtin_at("cave_entrance", "no_drop");
+        run(&mut g, &["take note"]);
+        g.player.location = "ancient_chamber".into();
+        let out = run(&mut g, &["drop note"]);
+        assert!(out.contains("Es un lugar sagrado"), "{out}");
+        assert!(carries(&g, "note"));
+        g.player.location = "cave_entrance".into();
+        run(&mut g, &["drop note"]);
+        assert!(!carries(&g, "note"));
+        assert!(g.current_room().items.contains(&"note".to_string()));
+    }
+
+    #[test]
+    fn fixed_switch_unlocks_a_remote_exit() {
+        let mut g = builtin_at("cave_entrance", "switch");
+        let out = run(&mut g, &["use palanca"]);
+        assert!(out.contains("Oyes un mecanismo a lo lejos."), "{out}");
+        assert_eq!(g.world.rooms["ancient_chamber"].flags.get("locked_down"), Some(&false));
+        assert!(!carries(&g, "lever"));
+    }
+
+    /// Observador que apunta los eventos en una lista compartida con la prueba.
+    #[derive(Debug)]
+    struct Recorder(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+    impl GameObserver for Recorder {
+        fn on_room_change(&mut self, from: &str, to: &str) {
+            self.0.borrow_mut().push(format!("move {from} {to}"));
+        }
+        fn on_item_taken(&mut self, item: &str) {
+            self.0.borrow_mut().push(format!("take {item}"));
+        }
+        fn on_item_dropped(&mut self, item: &str) {
+            self.0.borrow_mut().push(format!("drop {item}"));
+        }
+        fn on_item_put(&mut self, item: &str, container: &str) {
+            self.0.borrow_mut().push(format!("put {item} {container}"));
+        }
+    }
+
+    #[test]
+    fn observer_receives_take_move_drop_and_put() {
+        let events = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut g = builtin_at("cave_entrance", "observer");
+        g.set_observer(Box::new(Recorder(events.clone())));
+        run(&mut g, &["take torch", "take note", "go north", "go south", "drop note"]);
+        g.player.location = "crypt".into();
+        run(&mut g, &["open cofre", "put torch in cofre"]);
+        assert_eq!(
+            *events.borrow(),
+            [
+                "take torch",
+                "take note",
+                "move cave_entrance narrow_passage",
+                "move narrow_passage cave_entrance",
+                "drop note",
+                "put torch chest",
+            ]
+        );
+    }
+
+    #[test]
+    fn poison_drains_health_each_turn_until_death() {
+        let mut g = builtin_at("cave_entrance", "poison");
+        give(&mut g, "amulet");
+        g.player.health = 12;
+        run(&mut g, &["go north"]);
+        assert_eq!(g.player.health, 8);
+        let out = run(&mut g, &["go south"]);
+        assert_eq!(g.player.health, 4);
+        assert!(out.contains("Te sientes muy débil"), "{out}");
+        let out = run(&mut g, &["go north"]);
+        assert!(out.contains("Has muerto."), "{out}");
+        assert!(g.ended);
+        assert_eq!(g.outcome, Outcome::Death);
+    }
+
+    #[test]
+    fn dropping_the_poison_stops_the_drain() {
+        let mut g = builtin_at("cave_entrance", "poison_drop");
+        give(&mut g, "amulet");
+        run(&mut g, &["go north", "drop amulet", "go south"]);
+        assert_eq!(g.player.health, 96);
+    }
+
+    #[test]
+    fn messages_use_the_article_of_each_gender() {
+        let mut g = builtin_at("narrow_passage", "gender");
+        give(&mut g, "torch");
+        g.world.items.get_mut("torch").unwrap().state = "lit".into();
+        let out = run(&mut g, &["take llave vieja"]);
+        assert!(out.contains("Tomaste la llave vieja."), "{out}");
+        give(&mut g, "amulet");
+        let out = run(&mut g, &["drop amuleto negro"]);
+        assert!(out.contains("Dejaste el amuleto negro."), "{out}");
+    }
+
+    #[test]
+    fn items_without_gender_keep_the_bare_name() {
+        let mut g = builtin_at("cave_entrance", "no_gender");
+        g.world.items.get_mut("note").unwrap().gender = None;
+        let out = run(&mut g, &["take nota arrugada"]);
+        assert!(out.contains("Tomaste nota arrugada."), "{out}");
+        let plural = Item { name: "guantes".into(), gender: Some("m".into()), plural: true, ..Default::default() };
+        assert_eq!(plural.the_name(), "los guantes");
+        assert_eq!(plural.a_name(), "unos guantes");
+    }
+
+    #[test]
+    fn low_fuel_warns_once_before_the_torch_goes_out() {
+        let mut g = builtin_at("cave_entrance", "fuel_warning");
+        give(&mut g, "torch");
+        let torch = g.world.items.get_mut("torch").unwrap();
+        torch.state = "lit".into();
+        torch.fuel = Some(6);
+        let out = run(&mut g, &["go north", "go south", "go north", "go south", "go north", "go south"]);
+        assert_eq!(out.matches("La antorcha parpadea; pronto se apagará.").count(), 1, "{out}");
+        let warned = out.find("parpadea").unwrap();
+        let out_at = out.find("Tu antorcha se consume.").expect(&out);
+        assert!(warned < out_at);
+        assert!(!g.world.items["torch"].gives_light());
+    }
+
+    #[test]
+    fn darkness_blocks_movement_only_when_the_world_asks() {
+        let mut g = builtin_at("narrow_passage", "dark_walk");
+        run(&mut g, &["go south"]);
+        assert_eq!(g.player.location, "cave_entrance");
+
+        let mut world = build_world();
+        world.dark_blocks_movement = true;
+        let mut g = game(world, "dark_blocked");
+        g.player.location = "narrow_passage".into();
+        let out = run(&mut g, &["go south"]);
+        assert!(out.contains("No te atreves a avanzar a ciegas."), "{out}");
+        assert_eq!(g.player.location, "narrow_passage");
+    }
+
+    #[test]
+    fn torch_state_drives_its_description_and_the_light() {
+        let mut g = builtin_at("narrow_passage", "torch_state");
+        give(&mut g, "torch");
+        assert!(!g.has_light());
+        let out = run(&mut g, &["examine antorcha"]);
+        assert!(out.contains("apagada"), "{out}");
+        run(&mut g, &["use antorcha"]);
+        assert_eq!(g.world.items["torch"].state, "lit");
+        assert!(g.has_light());
+        let out = run(&mut g, &["examine antorcha encendida"]);
+        assert!(out.contains("Arde con una llama viva."), "{out}");
+        run(&mut g, &["extinguish antorcha encendida"]);
+        assert!(!g.has_light());
+    }
+
+    #[test]
+    fn count_cap_stops_taking_and_shows_in_inventory() {
+        let mut g = builtin_at("cave_entrance", "max_items");
+        g.player.max_items = Some(1);
+        let out = run(&mut g, &["take nota arrugada", "inventory"]);
+        assert!(out.contains("(1/1)"), "{out}");
+        let out = run(&mut g, &["take antorcha"]);
+        assert!(out.contains("No puedes llevar más cosas."), "{out}");
+        assert!(!carries(&g, "torch"));
+    }
+
+    #[test]
+    fn potion_heals_up_to_max_health_and_is_consumed() {
+        let mut g = builtin_at("crypt", "heal");
+        give(&mut g, "potion");
+        g.player.health = 40;
+        let out = run(&mut g, &["use poción roja"]);
+        assert!(out.contains("Te sientes mejor (+30)."), "{out}");
+        assert_eq!(g.player.health, 70);
+        assert!(!carries(&g, "potion"));
+
+        give(&mut g, "potion");
+        g.player.health = g.player.max_health - 10;
+        let out = run(&mut g, &["use poción roja"]);
+        assert!(out.contains("Te sientes mejor (+10)."), "{out}");
+        assert_eq!(g.player.health, g.player.max_health);
+    }
+
+    #[test]
+    fn read_note_prints_its_text_but_not_in_the_dark() {
+        let mut g = builtin_at("cave_entrance", "read");
+        let out = run(&mut g, &["read nota arrugada"]);
+        assert!(out.contains("La luz revela lo que temes."), "{out}");
+        let out = run(&mut g, &["read palanca"]);
+        assert!(out.contains("No hay nada que leer ahí."), "{out}");
+        run(&mut g, &["take nota arrugada", "go north"]);
+        let out = run(&mut g, &["read nota arrugada"]);
+        assert!(out.contains("Está demasiado oscuro para leer."), "{out}");
+    }
+
+    /// Sala mínima en JSON para los mundos escritos a mano en las pruebas.
+    fn room_json(key: &str, exits: serde_json::Value, items: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "key": key, "name": key, "desc": format!("Sala {key}."),
+            "exits": exits, "items": items, "flags": {}
+        })
+    }
+
+    fn item_json(key: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({ "key": key, "name": name, "desc": format!("Un {name}."), "portable": true, "effects": {} })
+    }
+
+    fn write_json(path: &Path, value: &serde_json::Value) {
+        fs::write(path, serde_json::to_string_pretty(value).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn includes_merge_region_files_with_later_keys_winning() {
+        let dir = temp_dir("include");
+        write_json(
+            &dir.join("world.json"),
+            &serde_json::json!({
+                "start": "hall",
+                "include": ["north.json", "south.json"],
+                "rooms": { "hall": room_json("hall", serde_json::json!({"north": "tower", "south": "cellar"}), &[]) }
+            }),
+        );
+        write_json(
+            &dir.join("north.json"),
+            &serde_json::json!({
+                "rooms": { "tower": room_json("tower", serde_json::json!({"south": "hall"}), &["coin"]) },
+                "items": { "coin": item_json("coin", "moneda de cobre") }
+            }),
+        );
+        write_json(
+            &dir.join("south.json"),
+            &serde_json::json!({
+                "rooms": { "cellar": room_json("cellar", serde_json::json!({"north": "hall"}), &[]) },
+                "items": { "coin": item_json("coin", "moneda de plata") }
+            }),
+        );
+        let world = World::load_from_file(dir.join("world.json").to_str().unwrap()).unwrap();
+        assert_eq!(world.rooms.keys().collect::<Vec<_>>(), ["hall", "tower", "cellar"]);
+        assert_eq!(world.items["coin"].name, "moneda de plata");
+
+        let mut g = game(world, "include_play");
+        let out = run(&mut g, &["go north", "take moneda de plata"]);
+        assert!(out.contains("Tomaste moneda de plata."), "{out}");
+    }
+
+    #[test]
+    fn circular_includes_are_reported() {
+        let dir = temp_dir("include_loop");
+        write_json(&dir.join("a.json"), &serde_json::json!({ "include": ["b.json"] }));
+        write_json(&dir.join("b.json"), &serde_json::json!({ "include": ["a.json"] }));
+        let err = World::load_from_file(dir.join("a.json").to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("Inclusión circular"), "{err}");
+    }
+
+    #[test]
+    fn failed_actions_do_not_spend_a_turn() {
+        let mut g = builtin_at("cave_entrance", "failed_turn");
+        give(&mut g, "amulet");
+        give(&mut g, "torch");
+        g.world.items.get_mut("torch").unwrap().state = "lit".into();
+        run(&mut g, &["take espada", "go west", "drop nada", "open palanca", "light antorcha"]);
+        assert_eq!(g.turns, 0);
+        assert_eq!(g.player.health, 100);
+        run(&mut g, &["use palanca", "take nota arrugada"]);
+        assert_eq!(g.turns, 2);
+        assert_eq!(g.player.health, 92);
+        assert_eq!(g.world.items["torch"].fuel, Some(38));
+        let out = run(&mut g, &["quit"]);
+        assert!(out.contains("Has hecho 2 movimientos"), "{out}");
+    }
+
+    #[test]
+    fn filler_words_are_dropped_only_at_the_start_of_a_phrase() {
+        assert_eq!(normalize_input("go to the north"), "go north");
+        assert_eq!(normalize_input("take the torch."), "take torch");
+        assert_eq!(normalize_input("take llave de la reja"), "take llave de la reja");
+        assert_eq!(normalize_input("take capa a rayas"), "take capa a rayas");
+        assert_eq!(normalize_input("take the key from the chest"), "take key from chest");
+        assert_eq!(normalize_input("apply the knife to the rope"), "apply knife to rope");
+    }
+
+    #[test]
+    fn names_with_filler_words_still_resolve() {
+        let mut g = builtin_at("cave_entrance", "stop_words");
+        let out = run(&mut g, &["take the torch.", "go to the north"]);
+        assert!(out.contains("Tomaste la antorcha."), "{out}");
+        assert_eq!(g.player.location, "narrow_passage");
+
+        g.world.items.get_mut("key_gate").unwrap().name = "llave de la reja".into();
+        g.world.items.get_mut("note").unwrap().name = "nota a rayas".into();
+        run(&mut g, &["use antorcha"]);
+        let out = run(&mut g, &["take llave de la reja"]);
+        assert!(out.contains("Tomaste la llave de la reja."), "{out}");
+        g.player.location = "cave_entrance".into();
+        let out = run(&mut g, &["take la nota a rayas"]);
+        assert!(out.contains("Tomaste la nota a rayas."), "{out}");
+    }
+
+    #[test]
+    fn exits_can_be_relocked_and_unlocked_with_the_key() {
+        let mut g = builtin_at("narrow_passage", "relock");
+        let locked = |g: &Game| g.world.rooms["narrow_passage"].flags["locked_north"];
+        let out = run(&mut g, &["unlock north"]);
+        assert!(out.contains("No tienes la llave."), "{out}");
+        assert!(locked(&g));
+        give(&mut g, "key_gate");
+        let out = run(&mut g, &["unlock north"]);
+        assert!(out.contains("desbloqueas la salida"), "{out}");
+        assert!(!locked(&g));
+        let out = run(&mut g, &["lock north"]);
+        assert!(out.contains("queda bloqueada"), "{out}");
+        assert!(locked(&g));
+        let out = run(&mut g, &["lock north"]);
+        assert!(out.contains("Ya está cerrada con llave."), "{out}");
+        run(&mut g, &["unlock north"]);
+        assert!(!locked(&g));
+    }
+
+    #[test]
+    fn feeling_in_the_dark_finds_exits_and_fixed_things_only() {
+        let mut g = builtin_at("narrow_passage", "feel");
+        let out = run(&mut g, &["feel"]);
+        assert!(out.contains("solo encuentras roca"), "{out}");
+        assert!(!out.contains("llave"), "{out}");
+        g.current_room_mut().items.push("altar".into());
+        let out = run(&mut g, &["feel"]);
+        assert!(out.contains("Palpas un altar de piedra."), "{out}");
+        assert!(out.contains(&exits_line(&g.world, g.current_room())), "{out}");
+    }
+
+    #[test]
+    fn an_item_placed_twice_is_warned_about() {
+        let mut world = build_world();
+        assert!(world.item_location_warnings().is_empty());
+        world.rooms.get_mut("crypt").unwrap().items.push("torch".into());
+        assert_eq!(
+            world.item_location_warnings(),
+            ["el objeto 'torch' aparece en varios sitios: sala cave_entrance, sala crypt"]
+        );
+        assert!(world.validate().iter().any(|f| !f.error && f.message.contains("'torch'")));
+    }
+
+    /// Pasillo de `n` salas, `r0` (inicio) a `r{n-1}`, unidas de sur a norte.
+    fn corridor(n: usize) -> World {
+        let rooms: serde_json::Map<String, serde_json::Value> = (0..n)
+            .map(|i| {
+                let mut exits = serde_json::Map::new();
+                if i > 0 {
+                    exits.insert("south".into(), format!("r{}", i - 1).into());
+                }
+                if i + 1 < n {
+                    exits.insert("north".into(), format!("r{}", i + 1).into());
+                }
+                (format!("r{i}"), room_json(&format!("r{i}"), exits.into(), &[]))
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({ "start": "r0", "rooms": rooms })).unwrap()
+    }
+
+    #[test]
+    fn go_with_a_count_walks_several_rooms() {
+        let mut g = game(corridor(5), "go_count");
+        let out = run(&mut g, &["go north 3"]);
+        assert_eq!(g.player.location, "r3");
+        assert_eq!(g.turns, 3);
+        assert!(out.contains("Pasas por r1.") && out.contains("Pasas por r2."), "{out}");
+        assert!(!out.contains("Pasas por r3."), "{out}");
+    }
+
+    #[test]
+    fn go_with_a_count_stops_at_a_wall() {
+        let mut g = game(corridor(3), "go_wall");
+        let out = run(&mut g, &["go north 5"]);
+        assert_eq!(g.player.location, "r2");
+        assert!(out.contains("No hay salida en esa dirección."), "{out}");
+        assert_eq!(g.turns, 2);
+    }
+
+    #[test]
+    fn examine_me_describes_the_player() {
+        let mut g = builtin_at("ancient_chamber", "examine_me");
+        g.player.health = 40;
+        give(&mut g, "torch");
+        g.world.items.get_mut("torch").unwrap().state = "lit".into();
+        g.player.wielded = Some("torch".into());
+        let out = run(&mut g, &["examine me"]);
+        assert!(out.contains(&format!("Eres {}.", g.player.name)), "{out}");
+        assert!(out.contains("Estás herido (40/100 de salud)."), "{out}");
+        assert!(out.contains("Empuñas la antorcha."), "{out}");
+        assert!(out.contains("La antorcha arde; le quedan 40 turnos."), "{out}");
+        assert_eq!(run(&mut g, &["examinarme"]), out);
+    }
+
+    #[test]
+    fn empty_world_file_says_so() {
+        let path = temp_dir("empty_world").join("world.json");
+        fs::write(&path, "  \n").unwrap();
+        let err = World::load_from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().starts_with("El archivo del mundo está vacío"), "{err}");
+    }
+
+    #[test]
+    fn broken_world_file_reports_line_and_column() {
+        let path = temp_dir("broken_world").join("world.json");
+        fs::write(&path, "{\n  \"start\": \"hall\",\n  \"rooms\": {,\n}\n").unwrap();
+        match World::load_from_file(path.to_str().unwrap()) {
+            Err(e @ GameError::ParseError { line: 3, .. }) => {
+                assert!(e.to_string().starts_with(&format!("Error al leer {} (línea 3, columna", path.display())), "{e}");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn running_out_of_turns_ends_the_game() {
+        let mut world = corridor(2);
+        world.turn_limit = Some(7);
+        let mut g = game(world, "turn_limit");
+        let out = run(&mut g, &["north", "south"]);
+        assert!(out.contains("Te quedan 5 turnos."), "{out}");
+        let saved = g.save_data();
+        let out = run(&mut g, &["north", "south", "north", "south"]);
+        assert!(out.contains("Te queda un solo turno."), "{out}");
+        let out = run(&mut g, &["north"]);
+        assert!(out.contains("Se te acabó el tiempo."), "{out}");
+        assert_eq!(g.outcome, Outcome::TimeUp);
+        assert!(g.ended);
+
+        // al cargar se respeta lo que quedaba, aunque el mundo ya no tenga límite
+        let mut g = game(corridor(2), "turn_limit_load");
+        g.restore(saved).unwrap();
+        let out = run(&mut g, &["north", "south", "north", "south", "north"]);
+        assert!(out.contains("Se te acabó el tiempo."), "{out}");
+    }
+
+    #[test]
+    fn effect_handlers_return_their_messages() {
+        let mut g = builtin_at("narrow_passage", "effect_lines");
+        give(&mut g, "key_gate");
+        give(&mut g, "potion");
+        assert_eq!(g.effect_unlocks("key_gate", "narrow_passage:north"), ["Usas la llave vieja y desbloqueas la salida north."]);
+        assert_eq!(g.effect_unlocks("key_gate", "narrow_passage:north"), ["Aquí no hay nada que desbloquear."]);
+        assert_eq!(g.effect_heal("potion", "30"), ["Ya estás en plena forma."]);
+        g.player.health = 50;
+        assert_eq!(g.effect_heal("potion", "30"), ["Usas la poción roja.", "Te sientes mejor (+30)."]);
+        assert_eq!(output(), "");
+
+        give(&mut g, "torch");
+        let out = run(&mut g, &["use antorcha"]);
+        assert!(out.starts_with("Enciendes la antorcha. La luz revela tu entorno.\n"), "{out}");
+        assert!(out.contains(&g.current_room().name), "{out}");
+    }
+
+    #[test]
+    fn quoted_names_are_single_arguments() {
+        assert_eq!(
+            tokenize(r#"put "llave in vieja" in cofre"#),
+            [("put".to_string(), false), ("llave in vieja".to_string(), true), ("in".to_string(), false), ("cofre".to_string(), false)]
+        );
+        let mut g = builtin_at("crypt", "quoted");
+        give(&mut g, "note");
+        g.world.items.get_mut("note").unwrap().name = "nota de la reja".into();
+        let out = run(&mut g, &[r#"open "cofre""#, r#"put "nota de la reja" in "cofre""#]);
+        assert!(out.contains("Metes la nota de la reja en el cofre."), "{out}");
+        let out = run(&mut g, &[r#"take "nota de la reja" from cofre"#]);
+        assert!(out.contains("Sacas la nota de la reja del cofre."), "{out}");
+    }
+
+    /// Lleva la antorcha encendida, para ver en las salas oscuras.
+    fn light_torch(g: &mut Game) {
+        give(g, "torch");
+        g.world.items.get_mut("torch").unwrap().state = "lit".into();
+    }
+
+    #[test]
+    fn npc_description_follows_the_room_flag() {
+        let mut g = builtin_at("narrow_passage", "npc_desc");
+        light_torch(&mut g);
+        g.world.npcs.get_mut("gatekeeper").unwrap().location = "narrow_passage".into();
+        let out = run(&mut g, &["examine guardián"]);
+        assert!(out.contains("bloquea el paso"), "{out}");
+        g.current_room_mut().flags.insert("locked_north".into(), false);
+        let out = run(&mut g, &["examine guardián"]);
+        assert!(out.contains("Un anciano encapuchado que vigila la reja."), "{out}");
+    }
+
+    /// Añade a la sala `room` un comerciante que vende y compra lo indicado.
+    fn add_merchant(g: &mut Game, room: &str, sells: &[(&str, u32)], buys: &[(&str, u32)]) {
+        let prices = |list: &[(&str, u32)]| list.iter().map(|(k, p)| (k.to_string(), *p)).collect();
+        g.world.npcs.insert(
+            "merchant".into(),
+            Npc {
+                key: "merchant".into(),
+                name: "la mercader".into(),
+                desc: "Una mercader con un fardo a la espalda.".into(),
+                location: room.into(),
+                patrol: vec![],
+                patrol_idx: 0,
+                desc_variants: vec![],
+                sells: prices(sells),
+                buys: prices(buys),
+                stock: None,
+                health: None,
+                attack: 0,
+                items: vec![],
+            },
+        );
+    }
+
+    #[test]
+    fn buying_spends_gold_and_refuses_when_short() {
+        let mut g = builtin_at("cave_entrance", "buy");
+        add_merchant(&mut g, "cave_entrance", &[("potion", 10), ("torch", 30)], &[]);
+        g.player.gold = 25;
+        let out = run(&mut g, &["buy poción roja"]);
+        assert!(out.contains("Compras la poción roja por 10 monedas."), "{out}");
+        assert!(carries(&g, "potion"));
+        assert_eq!(g.player.gold, 15);
+        let out = run(&mut g, &["buy antorcha", "inventory"]);
+        assert!(out.contains("No te alcanza: la antorcha cuesta 30 monedas y tienes 15."), "{out}");
+        assert!(out.contains("Oro: 15 monedas."), "{out}");
+        assert!(!carries(&g, "torch"));
+        assert_eq!(g.turns, 1);
+    }
+
+    #[test]
+    fn an_exit_requirement_is_checked_every_time() {
+        let mut g = builtin_at("ancient_chamber", "requires");
+        g.world.items.insert(
+            "rope".into(),
+            Item { key: "rope".into(), name: "cuerda".into(), gender: Some("f".into()), portable: true, ..Default::default() },
+        );
+        let room = g.current_room_mut();
+        room.flags.insert("locked_down".into(), false);
+        room.requires.insert(
+            "down".into(),
+            ExitRequirement { item: "rope".into(), message: Some("Necesitas una cuerda para bajar.".into()) },
+        );
+        let out = run(&mut g, &["go down"]);
+        assert!(out.contains("Necesitas una cuerda para bajar."), "{out}");
+        assert_eq!(g.player.location, "ancient_chamber");
+        give(&mut g, "rope");
+        light_torch(&mut g);
+        run(&mut g, &["go down"]);
+        assert_eq!(g.player.location, "crypt");
+        // sin la cuerda, la misma salida vuelve a negarse
+        let out = run(&mut g, &["drop cuerda", "go up", "go down"]);
+        assert!(out.contains("Necesitas una cuerda para bajar."), "{out}");
+        assert_eq!(g.player.location, "ancient_chamber");
+    }
+
+    #[test]
+    fn save_to_stdout_round_trips_through_a_buffer() {
+        let mut g = builtin_at("cave_entrance", "save_stream");
+        run(&mut g, &["take antorcha", "use antorcha", "go north"]);
+        let mut saved = run(&mut g, &["save -"]).into_bytes();
+        saved.extend_from_slice(b"look\n");
+        let mut reader = io::Cursor::new(saved);
+        let data = read_save_stream(&mut reader).unwrap();
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest.trim(), "look");
+
+        let mut fresh = builtin_at("cave_entrance", "save_stream_load");
+        fresh.restore(data).unwrap();
+        assert_eq!(fresh.player.location, "narrow_passage");
+        assert_eq!(fresh.turns, g.turns);
+        assert!(carries(&fresh, "torch"));
+        assert_eq!(fresh.world.items["torch"].state, "lit");
+        assert_eq!(fresh.world.items["torch"].fuel, g.world.items["torch"].fuel);
+    }
+
+    #[test]
+    fn using_a_mystery_item_reveals_its_description() {
+        let mut g = builtin_at("crypt", "identify");
+        light_torch(&mut g);
+        give(&mut g, "amulet");
+        let out = run(&mut g, &["examine amuleto negro"]);
+        assert!(out.contains("No sabrías decir para qué sirve."), "{out}");
+        assert!(!out.contains("obsidiana"), "{out}");
+        let out = run(&mut g, &["use amuleto negro"]);
+        assert!(out.contains("Ahora sabes qué es el amuleto negro."), "{out}");
+        let out = run(&mut g, &["examine amuleto negro"]);
+        assert!(out.contains("Un amuleto de obsidiana"), "{out}");
+        assert!(out.contains("envenena (4 por turno)"), "{out}");
+    }
+
+    #[test]
+    fn travel_walks_back_to_a_visited_room() {
+        let mut g = game(corridor(4), "travel");
+        run(&mut g, &["go north 3"]);
+        let out = run(&mut g, &["travel r5", "travel r0"]);
+        assert!(out.contains("No conoces ningún lugar con ese nombre."), "{out}");
+        assert!(out.contains("Viajas a r0."), "{out}");
+        assert_eq!(g.player.location, "r0");
+    }
+
+    #[test]
+    fn travel_ignores_visited_rooms_missing_from_the_world() {
+        let mut g = game(corridor(3), "travel_unknown");
+        run(&mut g, &["go north 2"]);
+        let mut saved = g.save_data();
+        saved.player.visited.push("gone".into());
+        g.restore(saved).unwrap();
+        assert!(!g.player.visited.contains(&"gone".to_string()));
+        // aunque el hueco aparezca durante la partida, `travel` no se cae
+        g.player.visited.insert(0, "gone".into());
+        let out = run(&mut g, &["travel gone", "travel r0"]);
+        assert!(out.contains("No conoces ningún lugar con ese nombre."), "{out}");
+        assert_eq!(g.player.location, "r0");
+    }
+
+    #[test]
+    fn a_hidden_message_shows_only_under_the_uv_lamp() {
+        let mut g = builtin_at("cave_entrance", "uv");
+        g.world.items.insert(
+            "uv_lamp".into(),
+            Item {
+                key: "uv_lamp".into(),
+                name: "lámpara violeta".into(),
+                portable: true,
+                effects: HashMap::from([("lights".into(), "true".into()), ("reveals".into(), "uv".into())]),
+                ..Default::default()
+            },
+        );
+        g.world.items.insert(
+            "writing".into(),
+            Item {
+                key: "writing".into(),
+                name: "inscripción brillante".into(),
+                desc: "Dice: 'Baja por la cripta.'".into(),
+                visible_under: Some("uv".into()),
+                ..Default::default()
+            },
+        );
+        g.current_room_mut().items.push("writing".into());
+        light_torch(&mut g);
+        let out = run(&mut g, &["look", "examine inscripción brillante"]);
+        assert!(!out.contains("inscripción"), "{out}");
+        give(&mut g, "uv_lamp");
+        let out = run(&mut g, &["look", "examine inscripción brillante"]);
+        assert!(out.contains("inscripción brillante"), "{out}");
+        assert!(out.contains("Baja por la cripta."), "{out}");
+    }
+
+    #[test]
+    fn key_goes_into_the_chest_and_back_out() {
+        let mut g = builtin_at("crypt", "put_key");
+        light_torch(&mut g);
+        give(&mut g, "key_gate");
+        let out = run(&mut g, &["put llave vieja in cofre"]);
+        assert!(out.contains("está cerrado"), "{out}");
+        assert!(carries(&g, "key_gate"));
+        let out = run(&mut g, &["open cofre", "drop llave vieja en cofre"]);
+        assert!(out.contains("Metes la llave vieja en el cofre."), "{out}");
+        assert!(!carries(&g, "key_gate"));
+        assert!(g.world.items["chest"].contents.contains(&"key_gate".to_string()));
+        g.world.items.get_mut("chest").unwrap().max_contents = Some(3);
+        give(&mut g, "note");
+        let out = run(&mut g, &["put nota arrugada in cofre"]);
+        assert!(out.contains("El cofre está lleno."), "{out}");
+        let out = run(&mut g, &["take llave vieja from cofre"]);
+        assert!(out.contains("Sacas la llave vieja del cofre."), "{out}");
+        assert!(carries(&g, "key_gate"));
+    }
+
+    #[test]
+    fn renamed_directions_show_in_exits_and_old_names_still_work() {
+        let mut world = corridor(2);
+        world.direction_names = HashMap::from([("north".into(), "proa".into()), ("south".into(), "popa".into())]);
+        let mut g = game(world, "direction_names");
+        let out = run(&mut g, &["exits"]);
+        assert!(out.contains("proa"), "{out}");
+        assert!(!out.contains("north"), "{out}");
+        run(&mut g, &["go north"]);
+        assert_eq!(g.player.location, "r1");
+        run(&mut g, &["go popa"]);
+        assert_eq!(g.player.location, "r0");
+    }
+
+    #[test]
+    fn fuel_burns_only_while_the_torch_is_lit() {
+        let mut g = game(corridor(2), "light_extinguish");
+        let torch = build_world().items["torch"].clone();
+        g.world.items.insert("torch".into(), torch);
+        g.player.inventory.push("torch".into());
+        let out = run(&mut g, &["light antorcha", "north"]);
+        assert!(out.contains("Enciendes la antorcha."), "{out}");
+        assert!(g.has_light());
+        assert_eq!(g.world.items["torch"].fuel, Some(38));
+        let out = run(&mut g, &["apagar antorcha encendida", "south", "north"]);
+        assert!(out.contains("Apagas la antorcha."), "{out}");
+        assert!(!g.has_light());
+        assert_eq!(g.world.items["torch"].fuel, Some(38));
+        let out = run(&mut g, &["extinguish antorcha"]);
+        assert!(out.contains("La antorcha no da luz."), "{out}");
+    }
+
+    #[test]
+    fn snapshot_is_available_in_every_build() {
+        let mut g = builtin_at("cave_entrance", "snapshot");
+        run(&mut g, &["take antorcha"]);
+        let view = g.snapshot();
+        assert_eq!(view.room_key, "cave_entrance");
+        assert_eq!(view.inventory, ["antorcha"]);
+        assert_eq!(view.items_here, ["nota arrugada", "palanca"]);
+        assert_eq!(view.turns, 1);
+    }
+
+    #[cfg(feature = "debug-cmds")]
+    #[test]
+    fn view_prints_the_snapshot_in_debug_mode() {
+        let mut g = builtin_at("cave_entrance", "view");
+        g.debug = true;
+        let out = run(&mut g, &["view"]);
+        assert_eq!(out.trim(), serde_json::to_string_pretty(&g.snapshot()).unwrap());
+    }
+
+    #[cfg(not(feature = "debug-cmds"))]
+    #[test]
+    fn debug_commands_are_unknown_without_the_feature() {
+        let mut g = builtin_at("cave_entrance", "no_debug_cmds");
+        g.debug = true;
+        for cmd in ["view", "set health 5", "path crypt", "clearflags", "writeworld x.json"] {
+            let out = run(&mut g, &[cmd]);
+            assert_eq!(out.trim(), UNKNOWN_COMMAND, "{cmd}");
+        }
+        assert_eq!(g.player.health, 100);
+    }
+
+    /// Mundo integrado en el que se gana al llegar a la cripta; llevar el amuleto da
+    /// otro final.
+    fn crypt_win_world() -> World {
+        let mut world = build_world();
+        world.win = Some(Condition { room: Some("crypt".into()), ..Default::default() });
+        world.endings = vec![Ending {
+            condition: Condition { carrying: vec!["amulet".into()], ..Default::default() },
+            text: "Sales con el amuleto, y con él su maldición.".into(),
+        }];
+        world
+    }
+
+    #[test]
+    fn the_ending_depends_on_what_the_player_carries() {
+        let play = |name: &str, amulet: bool| {
+            let mut g = game(crypt_win_world(), name);
+            g.player.location = "ancient_chamber".into();
+            g.current_room_mut().flags.insert("locked_down".into(), false);
+            if amulet {
+                give(&mut g, "amulet");
+            }
+            let out = run(&mut g, &["go down"]);
+            assert_eq!(g.outcome, Outcome::Win);
+            out
+        };
+        let cursed = play("ending_amulet", true);
+        assert!(cursed.contains("Sales con el amuleto"), "{cursed}");
+        let plain = play("ending_default", false);
+        assert!(plain.contains(DEFAULT_ENDING), "{plain}");
+        assert!(!plain.contains("Sales con el amuleto"), "{plain}");
+    }
+
+    #[test]
+    fn a_full_inventory_offers_to_drop_something() {
+        let mut g = builtin_at("cave_entrance", "make_room");
+        g.player.max_items = Some(1);
+        run(&mut g, &["take nota arrugada"]);
+        g.replies = Some(["nota arrugada".to_string()].into());
+        let out = run(&mut g, &["take antorcha"]);
+        assert!(out.contains("¿Qué quieres soltar para hacer espacio? (o 'nada')"), "{out}");
+        assert!(out.contains("Dejaste la nota arrugada."), "{out}");
+        assert!(out.contains("Tomaste la antorcha."), "{out}");
+        assert_eq!(g.player.inventory, ["torch"]);
+
+        g.replies = Some(["nada".to_string()].into());
+        let out = run(&mut g, &["take nota arrugada"]);
+        assert!(out.contains("Te quedas como estás."), "{out}");
+        assert_eq!(g.player.inventory, ["torch"]);
+    }
+
+    #[test]
+    fn notes_are_listed_numbered_and_survive_save_and_load() {
+        let mut g = builtin_at("cave_entrance", "notes");
+        run(&mut g, &["note add la palanca abre algo abajo", "apuntar add el guardián patrulla"]);
+        let out = run(&mut g, &["note list"]);
+        assert!(out.contains("  1. la palanca abre algo abajo\n  2. el guardián patrulla"), "{out}");
+        run(&mut g, &["save", "note clear"]);
+        assert!(g.player.notes.is_empty());
+        let out = run(&mut g, &["load", "note"]);
+        assert!(out.contains("  2. el guardián patrulla"), "{out}");
+    }
+
+    #[test]
+    fn width_falls_back_layer_by_layer() {
+        let no_terminal = || None;
+        assert_eq!(pick_width(Some(60), Some("100"), || Some(120)), 60);
+        assert_eq!(pick_width(None, Some("100"), || Some(120)), 100);
+        assert_eq!(pick_width(Some(0), Some(" 100 "), no_terminal), 100);
+        assert_eq!(pick_width(None, Some("ancho"), || Some(120)), 120);
+        assert_eq!(pick_width(None, None, || Some(120)), 120);
+        assert_eq!(pick_width(None, Some("0"), no_terminal), DEFAULT_WIDTH);
+        assert_eq!(pick_width(None, None, no_terminal), DEFAULT_WIDTH);
+    }
+
+    #[test]
+    fn environment_fills_in_what_the_command_line_leaves_out() {
+        let env = |name: &str| match name {
+            "MUD_WORLD" => Some("mundos/barco.json".to_string()),
+            "MUD_SAVE_DIR" => Some("/tmp/partidas".to_string()),
+            _ => None,
+        };
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let opts = Options::parse(args(&[]), env).unwrap();
+        assert_eq!(opts.world.as_deref(), Some("mundos/barco.json"));
+        assert_eq!(opts.save_dir.as_deref(), Some("/tmp/partidas"));
+        let opts = Options::parse(args(&["--world", "otro.json"]), env).unwrap();
+        assert_eq!(opts.world.as_deref(), Some("otro.json"));
+        let opts = Options::parse(args(&[]), |_| None).unwrap();
+        assert_eq!(opts.world, None);
+    }
+
+    #[test]
+    fn applying_the_knife_cuts_the_rope_in_place() {
+        let mut g = builtin_at("cave_entrance", "apply");
+        let item = |key: &str, name: &str| Item {
+            key: key.into(),
+            name: name.into(),
+            gender: Some("m".into()),
+            portable: true,
+            ..Default::default()
+        };
+        let transforms = HashMap::from([("rope".into(), "cut_rope".into())]);
+        g.world.items.insert("knife".into(), Item { transforms, ..item("knife", "cuchillo") });
+        g.world.items.insert("rope".into(), Item { gender: Some("f".into()), ..item("rope", "cuerda") });
+        g.world.items.insert("cut_rope".into(), Item { gender: Some("f".into()), ..item("cut_rope", "cuerda cortada") });
+        give(&mut g, "knife");
+        g.current_room_mut().items.push("rope".into());
+        let out = run(&mut g, &["apply cuchillo to nota arrugada"]);
+        assert!(out.contains("No sirve para eso."), "{out}");
+        let out = run(&mut g, &["apply cuchillo a cuerda"]);
+        assert!(out.contains("cuerda cortada"), "{out}");
+        let here = &g.current_room().items;
+        assert!(here.contains(&"cut_rope".to_string()) && !here.contains(&"rope".to_string()));
+        assert_eq!(g.turns, 1);
+    }
+
+    #[test]
+    fn over_long_names_and_unbroken_descriptions_are_warned_about() {
+        let mut world = build_world();
+        assert!(world.text_warnings().is_empty());
+        world.items.get_mut("torch").unwrap().name = "a".repeat(61);
+        world.rooms.get_mut("crypt").unwrap().desc = "x".repeat(201);
+        let warnings = world.text_warnings();
+        assert!(warnings.contains(&"objeto torch: el nombre tiene 61 caracteres (máximo 60)".to_string()), "{warnings:?}");
+        assert!(warnings.contains(&"sala crypt: la descripción tiene 201 caracteres seguidos sin espacios".to_string()), "{warnings:?}");
+        assert!(world.validate().iter().any(|f| !f.error && f.message.contains("61 caracteres")));
+    }
+
+    #[test]
+    fn saves_in_different_profiles_do_not_collide() {
+        let mut g = builtin_at("cave_entrance", "profiles");
+        let out = run(&mut g, &["profile ana"]);
+        assert!(out.contains("Perfil creado: ana"), "{out}");
+        run(&mut g, &["save partida"]);
+        g.player.location = "crypt".into();
+        run(&mut g, &["profile luis", "save partida"]);
+        let out = run(&mut g, &["saves"]);
+        assert!(out.contains("Partidas del perfil luis:\n  partida"), "{out}");
+        run(&mut g, &["profile ana", "load partida"]);
+        assert_eq!(g.player.location, "cave_entrance");
+        run(&mut g, &["profile luis", "load partida"]);
+        assert_eq!(g.player.location, "crypt");
+        let out = run(&mut g, &["profile"]);
+        assert!(out.contains("Perfil activo: luis\nOtros perfiles: ana"), "{out}");
+    }
+
+    #[test]
+    fn looking_under_the_altar_finds_the_key_once() {
+        let mut g = builtin_at("ancient_chamber", "look_under");
+        for r in g.world.rooms.values_mut() {
+            r.items.retain(|k| k != "key_gate");
+        }
+        g.world.items.get_mut("altar").unwrap().under = vec!["key_gate".into()];
+        let out = run(&mut g, &["look behind altar"]);
+        assert!(out.contains("No hay nada detrás del altar de piedra."), "{out}");
+        let out = run(&mut g, &["look under altar"]);
+        assert!(out.contains("Debajo del altar de piedra: llave vieja."), "{out}");
+        run(&mut g, &["take llave vieja", "save", "load"]);
+        assert!(carries(&g, "key_gate"));
+        let out = run(&mut g, &["look under altar"]);
+        assert!(out.contains("No hay nada debajo del altar de piedra."), "{out}");
+    }
+
+    #[test]
+    fn a_lock_nothing_opens_is_warned_about() {
+        let mut world = build_world();
+        assert!(world.keyless_lock_warnings().is_empty());
+        world.rooms.get_mut("crypt").unwrap().flags.insert("locked_up".into(), true);
+        assert_eq!(world.keyless_lock_warnings(), ["crypt up está cerrada y ningún objeto la abre"]);
+        world.items.shift_remove("key_gate");
+        assert_eq!(
+            world.keyless_lock_warnings(),
+            ["narrow_passage north está cerrada y ningún objeto la abre", "crypt up está cerrada y ningún objeto la abre"]
+        );
+        assert!(world.validate().iter().any(|f| !f.error && f.message.contains("crypt up")));
+    }
+
+    #[test]
+    fn bought_torches_are_new_instances_that_burn_on_their_own() {
+        let mut world = corridor(2);
+        let mut torch = build_world().items["torch"].clone();
+        torch.template = true;
+        world.items.insert("torch".into(), torch);
+        world.rooms.get_mut("r0").unwrap().items.push("torch".into());
+        world.instantiate_all();
+        let mut g = game(world.clone(), "buy_instances");
+        add_merchant(&mut g, "r0", &[("torch", 5)], &[("torch", 2)]);
+        g.player.gold = 10;
+        run(&mut g, &["take antorcha", "light antorcha"]);
+        let out = run(&mut g, &["buy antorcha"]);
+        assert!(out.contains("Compras la antorcha por 5 monedas."), "{out}");
+        assert!(carries(&g, "torch@r0") && carries(&g, "torch@merchant"));
+        assert_eq!(g.world.items["torch@merchant"].prototype.as_deref(), Some("torch"));
+        run(&mut g, &["light torch@merchant", "north", "south"]);
+        assert_eq!(g.world.items["torch@r0"].fuel, Some(35));
+        assert_eq!(g.world.items["torch@merchant"].fuel, Some(37));
+        assert!(g.world.items["torch"].fuel == Some(40) && g.world.items["torch"].state == "unlit");
+
+        let out = run(&mut g, &["sell torch@r0"]);
+        assert!(out.contains("Vendes la antorcha por 2 monedas."), "{out}");
+        assert_eq!(g.player.gold, 7);
+        assert_eq!(g.world.npcs["merchant"].for_sale(&g.world.items), ["torch@r0"]);
+
+        // la compra sobrevive a cargar la partida en un mundo recién leído
+        let mut fresh = game(world, "buy_instances");
+        add_merchant(&mut fresh, "r0", &[("torch", 5)], &[("torch", 2)]);
+        let save = g.save_data();
+        assert_eq!(save_problems(&fresh.world, &save), Vec::<String>::new());
+        fresh.restore(save).unwrap();
+        assert!(carries(&fresh, "torch@merchant"));
+        assert_eq!(fresh.world.items["torch@merchant"].fuel, Some(36)); // vender también gasta un turno
+        assert_eq!(fresh.world.items["torch@merchant"].state, "lit");
+    }
+
+    #[test]
+    fn repeating_a_locked_exit_nudges_once_unless_disabled() {
+        let mut g = builtin_at("narrow_passage", "nudge");
+        let out = run(&mut g, &["go north", "go north"]);
+        assert!(!out.contains(NUDGE_LOCKED), "{out}");
+        let out = run(&mut g, &["go north"]);
+        assert_eq!(out.matches("La salida está bloqueada.").count(), 1, "{out}");
+        assert!(out.contains("Quizás necesites algo para pasar."), "{out}");
+        let out = run(&mut g, &["go north"]);
+        assert!(!out.contains(NUDGE_LOCKED), "{out}");
+
+        let mut g = Game::new(build_world(), Options { no_nudge: true, ..opts("no_nudge") });
+        g.player.location = "narrow_passage".into();
+        let out = run(&mut g, &["go north", "go north", "go north"]);
+        assert!(!out.contains(NUDGE_LOCKED), "{out}");
+    }
+
+    #[test]
+    fn the_transcript_holds_commands_and_responses() {
+        let dir = temp_dir("transcript");
+        let path = dir.join("sesion.txt").to_string_lossy().into_owned();
+        let mut g = builtin_at("cave_entrance", "transcript");
+        let lines = [format!("transcript on {path}"), "take antorcha".into(), "transcript off".into(), "inventory".into()];
+        g.replies = Some(lines.into());
+        g.loop_run();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("> take antorcha\nTomaste la antorcha.\n"), "{text}");
+        assert!(text.contains("> transcript off\nTranscripción detenida.\n"), "{text}");
+        assert!(text.starts_with(&format!("Transcripción en {path}.\n")), "{text}");
+    }
+
+    #[test]
+    fn a_weak_npc_falls_and_drops_what_it_carried() {
+        let mut g = builtin_at("cave_entrance", "attack");
+        g.world.items.insert(
+            "dagger".into(),
+            Item {
+                key: "dagger".into(),
+                name: "daga".into(),
+                portable: true,
+                effects: HashMap::from([("damage".into(), "3".into())]),
+                gender: Some("f".into()),
+                ..Default::default()
+            },
+        );
+        give(&mut g, "dagger");
+        for r in g.world.rooms.values_mut() {
+            r.items.retain(|k| k != "key_gate");
+        }
+        g.world.npcs.insert(
+            "rat".into(),
+            Npc {
+                key: "rat".into(),
+                name: "la rata".into(),
+                desc: "Una rata enorme con algo brillante entre los dientes.".into(),
+                location: "cave_entrance".into(),
+                patrol: vec![],
+                patrol_idx: 0,
+                desc_variants: vec![],
+                sells: HashMap::new(),
+                buys: HashMap::new(),
+                stock: None,
+                health: Some(5),
+                attack: 2,
+                items: vec!["key_gate".into()],
+            },
+        );
+        let health = g.player.health;
+        let out = run(&mut g, &["wield daga", "attack rata"]);
+        assert!(out.contains("Golpeas con la daga: la rata pierde 3 de salud."), "{out}");
+        assert!(out.contains("La rata te devuelve el golpe (2 de daño"), "{out}");
+        assert_eq!(g.player.health, health - 2);
+        run(&mut g, &["save", "load"]);
+        assert_eq!(g.world.npcs["rat"].health, Some(2));
+        let out = run(&mut g, &["attack rata"]);
+        assert!(out.contains("La rata cae y ya no se levanta."), "{out}");
+        assert!(out.contains("Deja caer: llave vieja."), "{out}");
+        assert!(!g.world.npcs.contains_key("rat"));
+        assert!(g.current_room().items.contains(&"key_gate".to_string()));
+        assert_eq!(g.player.health, health - 2);
+    }
+
+    #[test]
+    fn world_commands_are_rejected_after_death() {
+        let mut g = builtin_at("cave_entrance", "after_death");
+        output();
+        g.die("Una piedra cae del techo.");
+        let out = output();
+        assert!(out.contains("Has muerto.\n\nEl juego ha terminado. Puedes usar restart, load, score o quit."), "{out}");
+        let out = run(&mut g, &["take antorcha", "go north"]);
+        assert_eq!(out, "El juego ha terminado.\nEl juego ha terminado.\n");
+        assert!(!carries(&g, "torch"));
+        assert_eq!(g.player.location, "cave_entrance");
+        assert!(g.running);
+        let out = run(&mut g, &["score"]);
+        assert!(!out.contains("El juego ha terminado."), "{out}");
+        run(&mut g, &["restart", "take antorcha"]);
+        assert!(!g.ended);
+        assert!(carries(&g, "torch"));
+    }
+
+    #[test]
+    fn lists_join_with_each_languages_conjunction() {
+        assert_eq!(join_list(&["la antorcha"], "es"), "la antorcha");
+        assert_eq!(join_list(&["la antorcha", "la nota"], "es"), "la antorcha y la nota");
+        assert_eq!(join_list(&["la antorcha", "la nota", "la llave"], "es"), "la antorcha, la nota y la llave");
+        assert_eq!(join_list(&["agua", "hilo"], "es"), "agua e hilo");
+        assert_eq!(join_list(&["the torch"], "en"), "the torch");
+        assert_eq!(join_list(&["the torch", "the note"], "en"), "the torch and the note");
+        assert_eq!(join_list(&["the torch", "the note", "the key"], "en"), "the torch, the note, and the key");
+
+        let parsed = Options::parse(Vec::<String>::new(), |name| (name == "MUD_LANG").then(|| "en_US.UTF-8".to_string())).unwrap();
+        assert_eq!(parsed.lang, "en");
+        let mut g = Game::new(build_world(), Options { lang: parsed.lang, ..opts("lang_en") });
+        let out = run(&mut g, &["look"]);
+        assert!(out.contains("Ves aquí: nota arrugada, antorcha, and palanca"), "{out}");
+    }
+
+    #[test]
+    fn a_torch_in_a_lightproof_bag_leaves_the_room_dark() {
+        let mut g = builtin_at("narrow_passage", "lightproof");
+        light_torch(&mut g);
+        g.world.items.insert(
+            "bag".into(),
+            Item {
+                key: "bag".into(),
+                name: "bolsa de cuero".into(),
+                portable: true,
+                container: true,
+                suppresses_light: true,
+                gender: Some("f".into()),
+                ..Default::default()
+            },
+        );
+        give(&mut g, "bag");
+        assert!(!g.in_darkness());
+        run(&mut g, &["put antorcha in bolsa de cuero"]);
+        assert!(g.world.items["bag"].contents.contains(&"torch".to_string()));
+        assert!(g.in_darkness());
+        let out = run(&mut g, &["look"]);
+        assert!(!out.contains("Pasadizo Estrecho"), "{out}");
+        run(&mut g, &["take antorcha from bolsa de cuero"]);
+        assert!(carries(&g, "torch"));
+        assert!(!g.in_darkness());
+
+        // una bolsa corriente y abierta deja pasar la luz
+        g.world.items.get_mut("bag").unwrap().suppresses_light = false;
+        run(&mut g, &["put antorcha in bolsa de cuero"]);
+        assert!(!g.in_darkness());
+    }
+
+    #[test]
+    fn custom_unknown_command_message_and_suggestions_off() {
+        let mut world = build_world();
+        world.unknown_command_message = Some("'{input}' no significa nada aquí.".into());
+        let args = ["--suggestions", "off"].map(String::from);
+        let parsed = Options::parse(args, |_| None).unwrap();
+        let mut g = Game::new(world, Options { suggestions: parsed.suggestions, ..opts("unknown_off") });
+        let out = run(&mut g, &["bailar  la jota"]);
+        assert_eq!(out, "'bailar  la jota' no significa nada aquí.\n");
+        let out = run(&mut g, &["take antorha"]);
+        assert!(!out.contains("¿Quisiste decir"), "{out}");
+
+        let mut g = builtin_at("cave_entrance", "unknown_on");
+        let out = run(&mut g, &["bailar"]);
+        assert_eq!(out, format!("{UNKNOWN_COMMAND}\n"));
+        let out = run(&mut g, &["take antorha"]);
+        assert!(out.contains("¿Quisiste decir 'antorcha'?"), "{out}");
+    }
+
+    #[test]
+    fn scores_rank_by_points_then_fewest_turns() {
+        let mut g = builtin_at("cave_entrance", "scores");
+        let play = |g: &mut Game, commands: &[&str]| {
+            run(g, commands);
+            g.die("Una piedra cae del techo.");
+            run(g, &["restart"]);
+        };
+        play(&mut g, &["profile ana"]);
+        play(&mut g, &["profile luis", "go north"]);
+        play(&mut g, &["profile eva", "take antorcha"]);
+        g.assisted = true;
+        play(&mut g, &["profile zoe", "go north", "go south"]);
+        let out = run(&mut g, &["scores"]);
+        let pos = |name: &str| out.find(name).unwrap_or_else(|| panic!("{name}: {out}"));
+        assert!(pos("luis") < pos("ana") && pos("ana") < pos("eva"), "{out}");
+        assert!(out.contains("   1. luis            20 puntos     1 turnos"), "{out}");
+        assert!(!out.contains("zoe"), "{out}");
+        assert!(out.contains("(1 partidas asistidas no entran en la tabla.)"), "{out}");
+
+        fs::write(g.scores_path(), "{ no es json").unwrap();
+        let out = run(&mut g, &["scores"]);
+        assert!(out.ends_with("scores.json está dañado.\n"), "{out}");
+    }
+
+    #[test]
+    fn examine_all_describes_each_carried_item_even_in_the_dark() {
+        let mut g = builtin_at("narrow_passage", "examine_all");
+        let out = run(&mut g, &["examine all"]);
+        assert_eq!(out, "No llevas nada.\n");
+        give(&mut g, "note");
+        give(&mut g, "key_gate");
+        assert!(g.in_darkness());
+        let out = run(&mut g, &["examine inventario"]);
+        assert_eq!(
+            out,
+            "Nota arrugada:\nDice: 'La luz revela lo que temes.'\n\nLlave vieja:\nUna llave oxidada con una runa.\n"
+        );
+    }
+
+    #[test]
+    fn check_save_reports_a_tampered_save() {
+        let dir = temp_dir("check_save");
+        let g = builtin_at("cave_entrance", "check_save_game");
+        let path = dir.join("buena.json").to_string_lossy().into_owned();
+        write_json(Path::new(&path), &serde_json::to_value(g.save_data()).unwrap());
+        output();
+        assert_eq!(check_save(&path, None), 0);
+        assert!(output().contains("partida válida"));
+
+        let mut save = g.save_data();
+        save.version = SAVE_VERSION + 1;
+        save.player.location = "sotano".into();
+        save.player.inventory.push("espada_magica".into());
+        let path = dir.join("trucada.json").to_string_lossy().into_owned();
+        write_json(Path::new(&path), &serde_json::to_value(save).unwrap());
+        assert_eq!(check_save(&path, None), 1);
+        let out = output();
+        assert!(out.contains(&format!("FALLO: La partida es de la versión {}", SAVE_VERSION + 1)), "{out}");
+        assert!(out.contains("FALLO: el jugador está en la sala 'sotano', que no existe"), "{out}");
+        assert!(out.contains("FALLO: el inventario contiene 'espada_magica', que no existe"), "{out}");
+        assert!(out.contains(&format!("{path}: 3 problemas con")), "{out}");
+    }
+
+    #[test]
+    fn the_first_cross_message_prints_only_once() {
+        let msg = "Cruzas la reja chirriante por primera vez.";
+        let mut world = corridor(3);
+        world.rooms.get_mut("r0").unwrap().first_cross_message.insert("north".into(), msg.into());
+        let mut g = game(world, "first_cross");
+        let out = run(&mut g, &["north"]);
+        assert_eq!(out.matches(msg).count(), 1, "{out}");
+        let out = run(&mut g, &["south", "north"]);
+        assert!(!out.contains(msg), "{out}");
+        run(&mut g, &["save", "restart"]);
+        let out = run(&mut g, &["north"]);
+        assert!(out.contains(msg), "{out}");
+        run(&mut g, &["load", "south"]);
+        let out = run(&mut g, &["north"]);
+        assert!(!out.contains(msg), "{out}");
+    }
+
+    #[cfg(feature = "debug-cmds")]
+    #[test]
+    fn debug_set_changes_health_and_rejects_bad_values() {
+        let mut g = builtin_at("cave_entrance", "debug_set");
+        run(&mut g, &["set health 40"]);
+        assert_eq!(g.player.health, 100);
+        g.debug = true;
+        let out = run(&mut g, &["set health 40"]);
+        assert_eq!(out, "Salud: 40/100.\n");
+        assert_eq!(g.player.health, 40);
+        let out = run(&mut g, &["set health mucho", "set health 0", "set mana 3", "set location sotano"]);
+        assert!(out.contains("Número no válido: mucho"), "{out}");
+        assert!(out.contains("La salud tiene que estar entre 1 y 100."), "{out}");
+        assert!(out.contains("Campo desconocido: mana."), "{out}");
+        assert!(out.contains("No existe la sala sotano."), "{out}");
+        assert_eq!(g.player.health, 40);
+        assert_eq!(g.player.location, "cave_entrance");
+    }
+
+    #[test]
+    fn a_crowded_room_shows_only_the_item_count() {
+        let mut world = corridor(1);
+        for i in 1..=9 {
+            let key = format!("p{i}");
+            world.items.insert(key.clone(), serde_json::from_value(item_json(&key, &format!("piedra {i}"))).unwrap());
+            world.rooms.get_mut("r0").unwrap().items.push(key);
+        }
+        let mut g = game(world, "crowded");
+        let out = run(&mut g, &["look"]);
+        assert!(out.contains("Ves aquí: 9 objetos (escribe 'names' para verlos)."), "{out}");
+        let out = run(&mut g, &["names"]);
+        assert!(out.contains("piedra 9 (p9)"), "{out}");
+        let out = run(&mut g, &["take piedra 9", "look"]);
+        assert!(out.contains("Ves aquí: piedra 1, piedra 2,"), "{out}");
+        g.world.look_item_limit = Some(3);
+        let out = run(&mut g, &["look"]);
+        assert!(out.contains("Ves aquí: 8 objetos"), "{out}");
+    }
+
+    #[test]
+    fn the_same_seed_shuffles_the_same_way() {
+        let seeded = |seed: u64, name: &str| Options { shuffle: true, seed: Some(seed), ..opts(name) };
+        let layout = |w: &World| w.rooms.values().map(|r| (r.key.clone(), r.items.clone())).collect::<Vec<_>>();
+        let first = prepare_world(None, &seeded(12345, "seed_a")).unwrap();
+        let second = prepare_world(None, &seeded(12345, "seed_b")).unwrap();
+        assert_eq!(layout(&first), layout(&second));
+        assert!((1..20).any(|s| layout(&prepare_world(None, &seeded(s, "seed_c")).unwrap()) != layout(&first)));
+
+        let mut a = game(first, "seed_a");
+        let mut b = game(second, "seed_b");
+        let tour = ["look", "go north", "look", "go south", "seed"];
+        assert_eq!(run(&mut a, &tour), run(&mut b, &tour));
+        let mut g = Game::new(build_world(), seeded(12345, "seed_game"));
+        let out = run(&mut g, &["seed"]);
+        assert_eq!(out, "Semilla: 12345. Repite esta partida con --seed 12345.\n");
+    }
+
+    #[test]
+    fn a_bare_direction_walks_that_way() {
+        let mut g = game(corridor(3), "bare_direction");
+        run(&mut g, &["north"]);
+        assert_eq!(g.player.location, "r1");
+        run(&mut g, &["N"]);
+        assert_eq!(g.player.location, "r2");
+        let out = run(&mut g, &["north"]);
+        assert_eq!(g.player.location, "r2");
+        assert!(!out.contains(UNKNOWN_COMMAND), "{out}");
+        run(&mut g, &["s"]);
+        assert_eq!(g.player.location, "r1");
+        assert_eq!(g.turns, 3);
+    }
+
+    #[test]
+    fn a_torch_and_a_candle_burn_their_own_fuel() {
+        let mut world = corridor(2);
+        for r in world.rooms.values_mut() {
+            r.flags.insert("dark".into(), true);
+        }
+        let mut torch = build_world().items["torch"].clone();
+        torch.state = "lit".into();
+        torch.fuel = Some(10);
+        let mut candle = torch.clone();
+        candle.key = "candle".into();
+        candle.name = "vela".into();
+        candle.fuel = Some(2);
+        world.items.insert("torch".into(), torch);
+        world.items.insert("candle".into(), candle);
+        let mut g = game(world, "two_lights");
+        g.player.inventory = vec!["torch".into(), "candle".into()];
+        let out = run(&mut g, &["north", "south"]);
+        assert_eq!(out.matches("Tu vela se consume.").count(), 1, "{out}");
+        assert!(!out.contains("antorcha se consume"), "{out}");
+        assert_eq!(g.world.items["candle"].fuel, Some(0));
+        assert!(!g.world.items["candle"].gives_light());
+        assert!(!g.in_darkness());
+        let out = run(&mut g, &["north"]);
+        assert!(!out.contains("vela"), "{out}");
+        assert_eq!(g.world.items["torch"].fuel, Some(7));
+        assert_eq!(g.world.items["candle"].fuel, Some(0));
+    }
+
+    #[test]
+    fn rewinding_returns_to_a_named_checkpoint() {
+        let mut g = builtin_at("cave_entrance", "checkpoints");
+        run(&mut g, &["take antorcha"]);
+        let out = run(&mut g, &["checkpoint antes"]);
+        assert_eq!(out, "Punto de control 'antes' creado (turno 1).\n");
+        run(&mut g, &["drop antorcha", "go north", "take llave vieja"]);
+        assert!(carries(&g, "key_gate") && !carries(&g, "torch"));
+        let out = run(&mut g, &["rewind antes"]);
+        assert!(out.contains("Vuelves al punto de control 'antes'."), "{out}");
+        assert_eq!(g.player.location, "cave_entrance");
+        assert!(carries(&g, "torch") && !carries(&g, "key_gate"));
+        assert_eq!(g.turns, 1);
+        assert!(g.world.rooms["narrow_passage"].items.contains(&"key_gate".to_string()));
+        let out = run(&mut g, &["checkpoints", "rewind otro"]);
+        assert!(out.contains("  antes (turno 1, Entrada de la Cueva)"), "{out}");
+        assert!(out.contains("No hay ningún punto de control 'otro'."), "{out}");
+
+        for i in 1..MAX_CHECKPOINTS {
+            run(&mut g, &[&format!("checkpoint c{i}")]);
+        }
+        let out = run(&mut g, &["checkpoint uno_mas", "checkpoint antes"]);
+        assert!(out.contains(&format!("Ya tienes {MAX_CHECKPOINTS} puntos de control")), "{out}");
+        assert!(out.contains("Punto de control 'antes' creado"), "{out}");
+    }
+
+    #[test]
+    fn an_ending_can_ask_for_any_item_with_a_tag() {
+        let play = |name: &str, carried: Option<&str>| {
+            let mut world = crypt_win_world();
+            world.items.get_mut("key_gate").unwrap().tags = vec!["llave".into()];
+            world.items.get_mut("note").unwrap().tags = vec!["papel".into()];
+            world.endings = vec![Ending {
+                condition: Condition { carrying_tag: vec!["llave".into()], ..Default::default() },
+                text: "Cierras la cripta tras de ti con la llave.".into(),
+            }];
+            let mut g = game(world, name);
+            g.player.location = "ancient_chamber".into();
+            g.current_room_mut().flags.insert("locked_down".into(), false);
+            if let Some(key) = carried {
+                give(&mut g, key);
+            }
+            run(&mut g, &["go down"])
+        };
+        let out = play("tag_key", Some("key_gate"));
+        assert!(out.contains("Cierras la cripta tras de ti con la llave."), "{out}");
+        let out = play("tag_paper", Some("note"));
+        assert!(out.contains(DEFAULT_ENDING), "{out}");
+        let out = play("tag_none", None);
+        assert!(!out.contains("Cierras la cripta"), "{out}");
+    }
+
+    #[test]
+    fn an_oversized_line_is_truncated_and_the_rest_skipped() {
+        let long = "x".repeat(MAX_INPUT_LEN + 100);
+        let mut input = io::Cursor::new(format!("{long}\nlook\n"));
+        let mut buf = String::new();
+        assert_eq!(read_input_line(&mut input, &mut buf).unwrap(), (MAX_INPUT_LEN, true));
+        assert_eq!(buf.len(), MAX_INPUT_LEN);
+        buf.clear();
+        assert_eq!(read_input_line(&mut input, &mut buf).unwrap(), (5, false));
+        assert_eq!(buf, "look\n");
+        buf.clear();
+        assert_eq!(read_input_line(&mut input, &mut buf).unwrap(), (0, false));
+
+        // un carácter cortado por la mitad se descarta
+        let mut input = io::Cursor::new(format!("{}ñ", "x".repeat(MAX_INPUT_LEN - 1)));
+        let mut buf = String::new();
+        assert_eq!(read_input_line(&mut input, &mut buf).unwrap(), (MAX_INPUT_LEN, true));
+        assert_eq!(buf, "x".repeat(MAX_INPUT_LEN - 1));
+    }
+
+    #[test]
+    fn verbs_lists_each_alias_under_its_verb() {
+        let mut g = builtin_at("cave_entrance", "verbs");
+        let out = run(&mut g, &["verbs"]);
+        assert!(out.starts_with("Verbos (y sinónimos):\n"), "{out}");
+        assert!(out.contains("\n  examine: x, examinar\n"), "{out}");
+        assert!(out.contains("\n  note: notes, apuntar\n"), "{out}");
+        assert!(out.contains("\n  quit\n"), "{out}");
+        assert!(!out.contains("solution"), "{out}");
+        // y el alias funciona como su verbo
+        let out = run(&mut g, &["examinar antorcha"]);
+        assert!(out.contains("Una antorcha de madera"), "{out}");
+        g.allow_solution = true;
+        let out = run(&mut g, &["verbs"]);
+        assert!(out.contains("\n  solution: solucion\n"), "{out}");
+    }
+
+    #[test]
+    fn saveinfo_summarizes_a_save_without_loading_it() {
+        let mut g = builtin_at("cave_entrance", "saveinfo");
+        g.player.gold = 7;
+        run(&mut g, &["go north", "save partida", "go south", "take antorcha"]);
+        let out = run(&mut g, &["saveinfo partida"]);
+        let lines = [
+            "  Jugador: Hero",
+            "  Sala: narrow_passage",
+            "  Turnos: 1",
+            "  Salud: 100/100",
+            "  Oro: 7",
+            "  Salas visitadas: 2",
+            "  Puntos: 27",
+            "  Mundo: builtin",
+            &format!("  Formato: versión {SAVE_VERSION}"),
+        ];
+        assert!(out.ends_with(&format!("partida.json:\n{}\n", lines.join("\n"))), "{out}");
+        assert_eq!(g.player.location, "cave_entrance");
+        assert_eq!(g.turns, 3);
+
+        let path = slot_path(g.save_dir.as_deref(), &g.profile, Some("rota"));
+        fs::write(&path, "{ \"player\": ").unwrap();
+        let out = run(&mut g, &["saveinfo rota"]);
+        assert!(out.starts_with(&format!("No se puede leer la partida {path}: ")), "{out}");
+        assert_eq!(save_info(&path), EXIT_ERROR);
+    }
+
+    #[test]
+    fn climb_enter_and_exit_pick_the_matching_exit() {
+        let rooms = serde_json::json!({
+            "patio": room_json("patio", serde_json::json!({ "up": "torre", "cabaña": "cabana", "north": "huerto" }), &[]),
+            "torre": room_json("torre", serde_json::json!({ "down": "patio" }), &[]),
+            "cabana": room_json("cabana", serde_json::json!({ "out": "patio", "in": "despensa" }), &[]),
+            "despensa": room_json("despensa", serde_json::json!({ "out": "cabana" }), &[]),
+            "huerto": room_json("huerto", serde_json::json!({ "south": "patio" }), &[]),
+        });
+        let world: World = serde_json::from_value(serde_json::json!({ "start": "patio", "rooms": rooms })).unwrap();
+        let mut g = game(world, "go_verbs");
+        let steps = [
+            ("climb", "torre"),
+            ("climb", "torre"),
+            ("exit", "patio"),
+            ("entrar", "cabana"),
+            ("enter", "despensa"),
+            ("salir", "cabana"),
+            ("exit", "patio"),
+            ("exit", "patio"),
+        ];
+        for (cmd, room) in steps {
+            run(&mut g, &[cmd]);
+            assert_eq!(g.player.location, room, "{cmd}");
+        }
+        let out = run(&mut g, &["go north", "climb", "enter", "exit"]);
+        assert!(out.contains("No puedes trepar aquí.\n"), "{out}");
+        assert!(out.contains("No ves por dónde entrar.\n"), "{out}");
+        assert!(out.contains("No ves por dónde salir.\n"), "{out}");
+        assert_eq!(g.turns, 7);
+    }
+
+    #[test]
+    fn autosave_on_enter_and_goal() {
+        let autosave_opts = |events: &str, name: &str| {
+            let args = ["--autosave-on", events].map(String::from);
+            let parsed = Options::parse(args, |_| None).unwrap();
+            Options { autosave_on: parsed.autosave_on, ..opts(name) }
+        };
+        let autosave_path = |g: &Game| slot_path(g.save_dir.as_deref(), &g.profile, Some(AUTOSAVE_SLOT));
+
+        let mut g = Game::new(crypt_win_world(), autosave_opts("enter,goal", "autosave_enter_goal"));
+        let out = run(&mut g, &["go north"]);
+        assert_eq!(out.matches("Juego guardado en").count(), 1, "{out}");
+        assert_eq!(read_save(&autosave_path(&g)).unwrap().player.location, "narrow_passage");
+        let out = run(&mut g, &["go south"]);
+        assert!(!out.contains("Juego guardado en"), "{out}");
+        g.player.location = "ancient_chamber".into();
+        g.current_room_mut().flags.insert("locked_down".into(), false);
+        // entrar en la cripta y ganar a la vez: un solo guardado
+        let out = run(&mut g, &["go down"]);
+        assert_eq!(g.outcome, Outcome::Win);
+        assert_eq!(out.matches("Juego guardado en").count(), 1, "{out}");
+        assert_eq!(read_save(&autosave_path(&g)).unwrap().player.location, "crypt");
+
+        let mut g = Game::new(crypt_win_world(), autosave_opts("goal", "autosave_goal"));
+        let out = run(&mut g, &["go north"]);
+        assert!(!out.contains("Juego guardado en"), "{out}");
+        g.player.location = "ancient_chamber".into();
+        g.current_room_mut().flags.insert("locked_down".into(), false);
+        let out = run(&mut g, &["go down"]);
+        assert!(out.contains("Juego guardado en"), "{out}");
+        assert_eq!(read_save(&autosave_path(&g)).unwrap().player.location, "crypt");
+        // el autoguardado del objetivo se carga como partida ganada, no para seguir jugando
+        let saved = autosave_path(&g);
+        let mut g = Game::new(crypt_win_world(), opts("autosave_goal_load"));
+        let out = run(&mut g, &[&format!("load {saved}")]);
+        assert!(out.contains("Esta partida ya había terminado."), "{out}");
+        assert!(g.ended);
+        assert_eq!(g.outcome, Outcome::Win);
+        let out = run(&mut g, &["go up"]);
+        assert!(out.contains("El juego ha terminado."), "{out}");
+        assert_eq!(g.player.location, "crypt");
+
+        assert!(Options::parse(["--autosave-on", "enter,gol"].map(String::from), |_| None).is_err());
+    }
+
+    #[test]
+    fn a_misspelled_room_flag_is_warned_about() {
+        let mut world = build_world();
+        world.rooms.get_mut("crypt").unwrap().flags.insert("lockd_north".into(), true);
+        // sin patrones declarados no se comprueban las banderas
+        assert!(world.flag_warnings().is_empty());
+        world.flag_patterns = vec!["water".into(), "rune_*".into()];
+        world.rooms.get_mut("crypt").unwrap().flags.insert("rune_glowing".into(), true);
+        assert_eq!(world.flag_warnings(), ["la sala crypt usa la bandera desconocida 'lockd_north'"]);
+        assert!(world.validate().iter().any(|f| !f.error && f.category == "banderas" && f.message.contains("'lockd_north'")));
+    }
+
+    #[test]
+    fn a_lit_torch_on_the_floor_shows_its_lit_name() {
+        let mut g = builtin_at("cave_entrance", "lit_name");
+        let out = run(&mut g, &["look"]);
+        assert!(out.contains("Ves aquí: nota arrugada, antorcha y palanca"), "{out}");
+        run(&mut g, &["take antorcha", "light antorcha", "drop antorcha"]);
+        let out = run(&mut g, &["look"]);
+        assert!(out.contains("Ves aquí: nota arrugada, palanca y antorcha encendida"), "{out}");
+        run(&mut g, &["take antorcha encendida", "extinguish antorcha"]);
+        let out = run(&mut g, &["inventory"]);
+        assert!(out.contains("Llevas: antorcha"), "{out}");
+        assert!(!out.contains("encendida"), "{out}");
+    }
+
+    #[test]
+    fn a_missing_world_file_is_world_not_found() {
+        let dir = temp_dir("world_not_found");
+        let missing = dir.join("no_existe.json");
+        match World::load_from_file(missing.to_str().unwrap()) {
+            Err(GameError::WorldNotFound(path)) => assert_eq!(path, missing),
+            other => panic!("{other:?}"),
+        }
+        // también si lo que falta es un archivo incluido
+        write_json(
+            &dir.join("world.json"),
+            &serde_json::json!({
+                "start": "hall",
+                "include": ["region.json"],
+                "rooms": { "hall": room_json("hall", serde_json::json!({}), &[]) }
+            }),
+        );
+        let err = World::load_from_file(dir.join("world.json").to_str().unwrap()).unwrap_err();
+        assert!(matches!(&err, GameError::WorldNotFound(path) if path.ends_with("region.json")), "{err:?}");
+        assert_eq!(err.to_string(), format!("No existe el archivo {}", dir.join("region.json").display()));
+    }
+
+    #[test]
+    fn the_demo_loops_until_stopped_then_hands_over_a_fresh_game() {
+        let mut g = builtin_at("cave_entrance", "demo");
+        let script = ["take antorcha", "go north", "quit"].map(String::from);
+        let mut checks = 0;
+        output();
+        g.play_demo(&script, Duration::ZERO, true, || {
+            checks += 1;
+            checks > 7
+        });
+        let out = output();
+        assert_eq!(out.matches("\n> take antorcha\nTomaste la antorcha.").count(), 3, "{out}");
+        assert_eq!(out.matches("\n> go north\n").count(), 2, "{out}");
+        assert_eq!(out.matches("Empiezas de nuevo.").count(), 3, "{out}");
+        assert!(out.contains("\nFin de la demostración. Te toca.\nEmpiezas de nuevo."), "{out}");
+        assert!(g.running && !g.demo);
+        assert_eq!((g.player.location.as_str(), g.turns), ("cave_entrance", 0));
+        assert!(!carries(&g, "torch"));
+
+        // sin bucle (entrada que no es una terminal), el guion se juega una vez
+        g.play_demo(&script, Duration::ZERO, false, || false);
+        let out = output();
+        assert_eq!(out.matches("> take antorcha").count(), 1, "{out}");
+        assert_eq!(out.matches("Empiezas de nuevo.").count(), 1, "{out}");
+    }
+
+    #[test]
+    fn a_fading_clue_can_only_be_examined_so_many_times() {
+        let mut g = builtin_at("cave_entrance", "examine_limit");
+        g.world.items.get_mut("note").unwrap().examine_limit = Some(2);
+        let out = run(&mut g, &["examine nota arrugada", "examine nota arrugada"]);
+        assert_eq!(out.matches("Dice: 'La luz revela lo que temes.'").count(), 2, "{out}");
+        run(&mut g, &["save", "load"]);
+        let out = run(&mut g, &["examine nota arrugada"]);
+        assert_eq!(out, "Ya no distingues nada nuevo.\n");
+        // los demás objetos no tienen límite
+        let out = run(&mut g, &["examine antorcha", "examine antorcha", "examine antorcha"]);
+        assert!(!out.contains("Ya no distingues"), "{out}");
+    }
+
+    #[cfg(feature = "debug-cmds")]
+    #[test]
+    fn edited_descriptions_survive_writeworld_and_reload() {
+        let dir = temp_dir("writeworld");
+        let path = dir.join("world.json");
+        let mut g = game(build_world(), "writeworld");
+        g.debug = true;
+        // estado de partida que no debe acabar en el mundo escrito
+        let torch = g.world.items.get_mut("torch").unwrap();
+        torch.state = "lit".into();
+        torch.fuel = Some(3);
+        g.world.items.get_mut("note").unwrap().examined = 2;
+        let out = run(
+            &mut g,
+            &["setdesc room Una cueva recién pintada.", "setdesc item note Una nota nueva.", &format!("writeworld {}", path.display())],
+        );
+        assert!(out.contains("Mundo escrito"), "{out}");
+
+        let loaded = World::load_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.rooms["cave_entrance"].desc, "Una cueva recién pintada.");
+        assert_eq!(loaded.items["note"].desc, "Una nota nueva.");
+        // aparte de las dos descripciones, es el mundo original
+        let mut expected = build_world();
+        expected.rooms.get_mut("cave_entrance").unwrap().desc = "Una cueva recién pintada.".into();
+        expected.items.get_mut("note").unwrap().desc = "Una nota nueva.".into();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&expected).unwrap());
+    }
+
+    /// Mundo por partes: `world.json` sin salas y el pasillo r0 - r1 - r2 en `rooms/`.
+    fn split_corridor(name: &str) -> PathBuf {
+        let dir = temp_dir(name);
+        let rooms = dir.join(ROOMS_SUBDIR);
+        fs::create_dir_all(&rooms).unwrap();
+        write_json(&dir.join("world.json"), &serde_json::json!({ "start": "r0", "rooms": {}, "items": {} }));
+        write_json(&rooms.join("r0.json"), &room_json("r0", serde_json::json!({ "north": "r1" }), &[]));
+        write_json(&rooms.join("r1.json"), &room_json("r1", serde_json::json!({ "north": "r2", "south": "r0" }), &[]));
+        write_json(&rooms.join("r2.json"), &room_json("r2", serde_json::json!({ "south": "r1" }), &[]));
+        dir
+    }
+
+    #[test]
+    fn a_split_world_reads_only_the_rooms_that_are_visited() {
+        let dir = split_corridor("split_lazy");
+        let world = World::load_from_dir(&dir).unwrap();
+        assert_eq!(world.rooms.keys().collect::<Vec<_>>(), ["r0"]);
+        let mut g = game(world, "split_lazy_game");
+        run(&mut g, &["go north"]);
+        assert_eq!(g.player.location, "r1");
+        assert!(g.world.rooms.contains_key("r1"));
+        assert!(!g.world.rooms.contains_key("r2"));
+        // una clave que se sale de rooms/ no se lee
+        assert!(matches!(g.ensure_room("../world"), Err(GameError::Validation(_))));
+        // una sala que no se puede leer es un error, no una sala que falta
+        fs::write(dir.join(ROOMS_SUBDIR).join("r2.json"), "{").unwrap();
+        let out = run(&mut g, &["go north"]);
+        assert!(out.contains("Error al leer"), "{out}");
+        assert_eq!(g.player.location, "r1");
+    }
+
+    #[test]
+    fn shuffling_a_split_world_reads_every_room_first() {
+        let dir = split_corridor("split_shuffle");
+        let parsed = Options { shuffle: true, seed: Some(1), ..opts("split_shuffle_opts") };
+        let world = prepare_world(Some(dir.to_str().unwrap()), &parsed).unwrap();
+        assert_eq!(world.rooms.len(), 3);
+    }
+
+    #[test]
+    fn templates_in_containers_and_npc_inventories_get_their_own_instances() {
+        let mut w = build_world();
+        w.items.get_mut("torch").unwrap().template = true;
+        w.rooms.get_mut("cave_entrance").unwrap().items.retain(|k| k != "torch");
+        w.items.get_mut("chest").unwrap().contents.push("torch".into());
+        w.npcs.get_mut("gatekeeper").unwrap().items.push("torch".into());
+        w.items.get_mut("altar").unwrap().under.push("torch".into());
+        w.instantiate_all();
+        let in_chest = w.items["chest"].contents.last().unwrap().clone();
+        let carried = w.npcs["gatekeeper"].items.last().unwrap().clone();
+        let hidden = w.items["altar"].under[0].clone();
+        assert_eq!([in_chest.as_str(), carried.as_str(), hidden.as_str()], ["torch@chest", "torch@gatekeeper", "torch@altar"]);
+
+        let mut g = game(w, "template_places");
+        give(&mut g, &in_chest);
+        g.world.items.get_mut(&in_chest).unwrap().state = "lit".into();
+        run(&mut g, &["go north", "go south"]);
+        assert_eq!(g.world.items[&in_chest].fuel, Some(38));
+        assert_eq!(g.world.items[&carried].fuel, Some(40));
+        assert_eq!(g.world.items[&carried].state, "unlit");
+        assert_eq!(g.world.items[&hidden].fuel, Some(40));
+    }
+
+    /// Verbos de los brazos de `match verb` en `run_command`, sin los de depuración.
+    fn dispatched_verbs() -> Vec<String> {
+        let src = include_str!("lib.rs");
+        let body = &src[src.find("    pub fn run_command(").unwrap()..];
+        let body = &body[body.find("        match verb {\n").unwrap()..];
+        let body = &body[..body.find("            // una dirección a secas").unwrap()];
+        let mut verbs = Vec::new();
+        let mut debug_only = false;
+        for line in body.lines() {
+            if line == "            #[cfg(feature = \"debug-cmds\")]" {
+                debug_only = true;
+            } else if let Some(arm) = line.strip_prefix("            \"") {
+                let pattern = arm.split(" =>").next().unwrap().split(" if ").next().unwrap();
+                if !std::mem::take(&mut debug_only) {
+                    verbs.extend(pattern.split(" | ").map(|v| v.trim_matches('"').to_string()));
+                }
+            }
+        }
+        verbs.sort();
+        verbs.dedup();
+        verbs
+    }
+
+    #[test]
+    fn verbs_list_matches_the_dispatch() {
+        let mut listed: Vec<String> = VERBS.iter().map(|v| v.to_string()).collect();
+        listed.sort();
+        assert_eq!(listed, dispatched_verbs());
+
+        let mut g = builtin_at("cave_entrance", "verbs_dispatch");
+        g.allow_solution = true;
+        for verb in VERBS {
+            // ya asistida, para que `solution` no pregunte por la entrada estándar
+            g.assisted = true;
+            let out = run(&mut g, &[verb]);
+            assert!(!out.contains(UNKNOWN_COMMAND), "{verb}: {out}");
+            g.ended = false;
+        }
+    }
+
+    #[test]
+    fn defeating_an_npc_autosaves_once() {
+        let args = ["--autosave-on", "defeat"].map(String::from);
+        let parsed = Options::parse(args, |_| None).unwrap();
+        let mut world = build_world();
+        world.npcs.insert(
+            "rat".into(),
+            Npc {
+                key: "rat".into(),
+                name: "la rata".into(),
+                desc: "Una rata enorme.".into(),
+                location: "cave_entrance".into(),
+                patrol: vec![],
+                patrol_idx: 0,
+                desc_variants: vec![],
+                sells: HashMap::new(),
+                buys: HashMap::new(),
+                stock: None,
+                health: Some(2),
+                attack: 0,
+                items: vec![],
+            },
+        );
+        let mut g = game(world, "autosave_defeat");
+        g.autosave_on = parsed.autosave_on;
+        let out = run(&mut g, &["attack rata"]);
+        assert!(!out.contains("Juego guardado en"), "{out}");
+        let out = run(&mut g, &["attack rata"]);
+        assert!(out.contains("La rata cae y ya no se levanta."), "{out}");
+        assert_eq!(out.matches("Juego guardado en").count(), 1, "{out}");
+        let saved = read_save(&slot_path(g.save_dir.as_deref(), &g.profile, Some(AUTOSAVE_SLOT))).unwrap();
+        assert_eq!(saved.defeated, ["rat"]);
+        assert!(saved.ended.is_none());
+    }
+
+    #[cfg(feature = "debug-cmds")]
+    #[test]
+    fn clearflags_restores_the_room_flags_and_leaves_the_items() {
+        let mut g = builtin_at("cave_entrance", "clearflags");
+        g.debug = true;
+        run(&mut g, &["take nota arrugada", "use palanca"]);
+        g.player.location = "ancient_chamber".into();
+        assert!(!g.current_room().flags["locked_down"]);
+        g.current_room_mut().items.push("note".into());
+        g.player.inventory.retain(|k| k != "note");
+        let items = g.current_room().items.clone();
+
+        let out = run(&mut g, &["clearflags"]);
+        assert!(out.contains("Banderas de la sala restauradas."), "{out}");
+        assert_eq!(g.current_room().flags, g.pristine.rooms["ancient_chamber"].flags);
+        assert!(g.current_room().flags["locked_down"]);
+        assert_eq!(g.current_room().items, items);
+    }
+
+    #[test]
+    fn a_missing_save_is_a_not_found_io_error() {
+        let mut g = game(build_world(), "missing_save");
+        let path = temp_dir("missing_save_file").join("nada.json");
+        match g.load(path.to_str().unwrap()) {
+            Err(GameError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            other => panic!("{other:?}"),
+        }
+    }
+}